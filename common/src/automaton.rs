@@ -0,0 +1,358 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::grid::Grid;
+
+// Coordinates on the infinite plane, same `(row, column)` order as the grid's.
+pub type PlaneCoordinates = (isize, isize);
+
+// The 3x3 block around a cell, `block[1][1]` being the cell itself.
+pub type Block<T> = [[T; 3]; 3];
+
+pub trait Rule<T> {
+    fn next(&self, block: &Block<T>) -> T;
+}
+
+impl<T, F: Fn(&Block<T>) -> T> Rule<T> for F {
+    fn next(&self, block: &Block<T>) -> T {
+        self(block)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseRuleError {
+    InvalidCharacter { position: usize, character: char },
+    WrongLength { expected: usize, found: usize },
+    InvalidLifeRule(String),
+}
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseRuleError::InvalidCharacter {
+                position,
+                character,
+            } => write!(
+                f,
+                "position {}: unexpected character '{}'",
+                position + 1,
+                character
+            ),
+            ParseRuleError::WrongLength { expected, found } => {
+                write!(f, "expected {} entries, found {}", expected, found)
+            }
+            ParseRuleError::InvalidLifeRule(rule) => {
+                write!(f, "expected a rule like B3/S23, got {}", rule)
+            }
+        }
+    }
+}
+
+impl Error for ParseRuleError {}
+
+// New state for every possible 3x3 block of dead (0) and alive (1) cells. The
+// block is read row by row into a 9-bit index, the top left cell being the most
+// significant bit.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LookupTable {
+    table: Vec<u8>,
+}
+
+impl LookupTable {
+    pub const SIZE: usize = 512;
+
+    pub fn new(table: Vec<u8>) -> Result<Self, ParseRuleError> {
+        if table.len() != Self::SIZE {
+            return Err(ParseRuleError::WrongLength {
+                expected: Self::SIZE,
+                found: table.len(),
+            });
+        }
+        Ok(Self { table })
+    }
+
+    pub fn index(block: &Block<u8>) -> usize {
+        block
+            .iter()
+            .flatten()
+            .fold(0, |index, cell| (index << 1) | (*cell & 1) as usize)
+    }
+
+    pub fn get(&self, index: usize) -> u8 {
+        self.table[index]
+    }
+}
+
+impl Rule<u8> for LookupTable {
+    fn next(&self, block: &Block<u8>) -> u8 {
+        self.table[Self::index(block)]
+    }
+}
+
+// `#` for alive, `.` for dead, as in the puzzle input.
+impl FromStr for LookupTable {
+    type Err = ParseRuleError;
+
+    fn from_str(table: &str) -> Result<Self, Self::Err> {
+        let table = table
+            .trim()
+            .chars()
+            .enumerate()
+            .map(|(position, character)| match character {
+                '.' => Ok(0),
+                '#' => Ok(1),
+                _ => Err(ParseRuleError::InvalidCharacter {
+                    position,
+                    character,
+                }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(table)
+    }
+}
+
+// Life-like rule: a dead cell comes alive with a number of alive neighbours
+// listed in `birth`, an alive one stays alive with a number listed in
+// `survival`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct LifeRule {
+    birth: [bool; 9],
+    survival: [bool; 9],
+}
+
+impl LifeRule {
+    pub fn game_of_life() -> Self {
+        "B3/S23".parse().unwrap()
+    }
+}
+
+impl Rule<u8> for LifeRule {
+    fn next(&self, block: &Block<u8>) -> u8 {
+        let alive = block.iter().flatten().filter(|cell| **cell == 1).count();
+        if block[1][1] == 1 {
+            self.survival[alive - 1] as u8
+        } else {
+            self.birth[alive] as u8
+        }
+    }
+}
+
+impl FromStr for LifeRule {
+    type Err = ParseRuleError;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseRuleError::InvalidLifeRule(rule.to_string());
+        let (birth, survival) = rule.trim().split_once('/').ok_or_else(invalid)?;
+        let counts = |part: &str, prefix: char| {
+            let mut part = part.chars();
+            if !part.next()?.eq_ignore_ascii_case(&prefix) {
+                return None;
+            }
+            let mut counts = [false; 9];
+            for digit in part {
+                counts[digit.to_digit(10).filter(|count| *count < 9)? as usize] = true;
+            }
+            Some(counts)
+        };
+
+        Ok(Self {
+            birth: counts(birth, 'B').ok_or_else(invalid)?,
+            survival: counts(survival, 'S').ok_or_else(invalid)?,
+        })
+    }
+}
+
+impl fmt::Display for LifeRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = |counts: &[bool; 9]| {
+            (0..9)
+                .filter(|count| counts[*count])
+                .map(|count| count.to_string())
+                .collect::<String>()
+        };
+        write!(f, "B{}/S{}", counts(&self.birth), counts(&self.survival))
+    }
+}
+
+// Cellular automaton on an infinite plane: a finite grid of cells surrounded
+// by infinitely many `background` cells. Every step grows the grid by one cell
+// on each side, which is as far as its cells can influence the plane, and
+// evolves the background as a block made only of background cells.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Automaton<T> {
+    cells: Grid<T>,
+    background: T,
+    // Plane coordinates of the grid's `(0, 0)` cell.
+    origin: PlaneCoordinates,
+    generation: usize,
+}
+
+impl<T: Copy> Automaton<T> {
+    pub fn new(cells: Grid<T>, background: T) -> Self {
+        Self {
+            cells,
+            background,
+            origin: (0, 0),
+            generation: 0,
+        }
+    }
+
+    // Places the grid's `(0, 0)` cell at `origin` instead of at `(0, 0)`.
+    pub fn with_origin(mut self, origin: PlaneCoordinates) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn cells(&self) -> &Grid<T> {
+        &self.cells
+    }
+
+    pub fn background(&self) -> T {
+        self.background
+    }
+
+    pub fn origin(&self) -> PlaneCoordinates {
+        self.origin
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn get(&self, (x, y): PlaneCoordinates) -> T {
+        let (x, y) = (x - self.origin.0, y - self.origin.1);
+        if x < 0 || y < 0 {
+            return self.background;
+        }
+        self.cells
+            .get((x as usize, y as usize))
+            .copied()
+            .unwrap_or(self.background)
+    }
+
+    pub fn block(&self, (x, y): PlaneCoordinates) -> Block<T> {
+        std::array::from_fn(|dx| {
+            std::array::from_fn(|dy| self.get((x + dx as isize - 1, y + dy as isize - 1)))
+        })
+    }
+
+    pub fn step<R: Rule<T> + ?Sized>(&mut self, rule: &R) {
+        let origin = (self.origin.0 - 1, self.origin.1 - 1);
+        let cells = Grid::from_fn(self.cells.width() + 2, self.cells.height() + 2, |(x, y)| {
+            rule.next(&self.block((origin.0 + x as isize, origin.1 + y as isize)))
+        });
+
+        self.background = rule.next(&[[self.background; 3]; 3]);
+        self.cells = cells;
+        self.origin = origin;
+        self.generation += 1;
+    }
+
+    pub fn run<R: Rule<T> + ?Sized>(&mut self, rule: &R, steps: usize) {
+        for _ in 0..steps {
+            self.step(rule);
+        }
+    }
+
+    pub fn into_parts(self) -> (Grid<T>, T) {
+        (self.cells, self.background)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alive(automaton: &Automaton<u8>) -> Vec<PlaneCoordinates> {
+        let (x_origin, y_origin) = automaton.origin();
+        automaton
+            .cells()
+            .cells()
+            .filter(|(_, cell)| **cell == 1)
+            .map(|((x, y), _)| (x as isize + x_origin, y as isize + y_origin))
+            .collect()
+    }
+
+    #[test]
+    fn test_life_rule() {
+        let rule = "b36/s23".parse::<LifeRule>().unwrap();
+
+        assert_eq!(rule.to_string(), "B36/S23");
+        assert_eq!(LifeRule::game_of_life().to_string(), "B3/S23");
+        assert!("B3S23".parse::<LifeRule>().is_err());
+        assert_eq!(
+            "B9/S23".parse::<LifeRule>(),
+            Err(ParseRuleError::InvalidLifeRule("B9/S23".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_blinker() {
+        let blinker = Grid::parse_chars(".#.\n.#.\n.#.", |c| Some((c == '#') as u8)).unwrap();
+        let mut automaton = Automaton::new(blinker, 0);
+
+        automaton.step(&LifeRule::game_of_life());
+        assert_eq!(alive(&automaton), vec![(1, 0), (1, 1), (1, 2)]);
+        assert_eq!(automaton.origin(), (-1, -1));
+        assert_eq!(automaton.get((1, 0)), 1);
+        assert_eq!(automaton.get((-100, 5)), 0);
+
+        automaton.step(&LifeRule::game_of_life());
+        assert_eq!(alive(&automaton), vec![(0, 1), (1, 1), (2, 1)]);
+        assert_eq!(automaton.generation(), 2);
+    }
+
+    #[test]
+    fn test_lookup_table() {
+        let mut table = vec![0; LookupTable::SIZE];
+        // Alive only when the top left neighbour is the sole alive cell, so a
+        // single cell moves diagonally.
+        table[0b1_0000_0000] = 1;
+        let table = LookupTable::new(table).unwrap();
+        let mut automaton = Automaton::new(Grid::new(1, 1, 1), 0);
+
+        automaton.run(&table, 3);
+
+        assert_eq!(alive(&automaton), vec![(3, 3)]);
+        let mut shifted = Automaton::new(Grid::new(1, 1, 1), 0).with_origin((-5, 2));
+        shifted.run(&table, 3);
+        assert_eq!(alive(&shifted), vec![(-2, 5)]);
+        assert_eq!(
+            LookupTable::index(&[[1, 0, 0], [0, 1, 0], [0, 0, 1]]),
+            0b100_010_001
+        );
+        assert_eq!(
+            "#.".parse::<LookupTable>(),
+            Err(ParseRuleError::WrongLength {
+                expected: 512,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_background_flips() {
+        // Every dead cell with no alive neighbours comes alive and every alive
+        // cell dies, so the background alternates.
+        let rule = "B0/S".parse::<LifeRule>().unwrap();
+        let mut automaton = Automaton::new(Grid::new(2, 2, 0), 0);
+
+        automaton.step(&rule);
+        assert_eq!(automaton.background(), 1);
+        automaton.step(&rule);
+        assert_eq!(automaton.background(), 0);
+        assert!(automaton.cells().iter().all(|cell| *cell == 0));
+    }
+
+    #[test]
+    fn test_closure_rule() {
+        let sum = |block: &Block<u32>| block.iter().flatten().sum::<u32>();
+        let mut automaton = Automaton::new(Grid::new(1, 1, 1), 0);
+
+        automaton.run(&sum, 2);
+
+        assert_eq!(automaton.cells().size(), (5, 5));
+        assert_eq!(automaton.get((0, 0)), 9);
+        assert_eq!(automaton.get((-2, -2)), 1);
+        assert_eq!(automaton.background(), 0);
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+pub type NodeId = usize;
+
+// Nodes are interned by name, so algorithms can work on plain indices and
+// per-node data can live in vectors instead of maps keyed by strings.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Graph<T> {
+    names: Vec<String>,
+    ids: HashMap<String, NodeId>,
+    attributes: Vec<T>,
+    adjacency: Vec<Vec<NodeId>>,
+}
+
+impl<T> Default for Graph<T> {
+    fn default() -> Self {
+        Self {
+            names: Vec::new(),
+            ids: HashMap::new(),
+            attributes: Vec::new(),
+            adjacency: Vec::new(),
+        }
+    }
+}
+
+impl<T> Graph<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_node(&mut self, name: &str, attributes: T) -> NodeId {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.attributes.push(attributes);
+        self.adjacency.push(Vec::new());
+        id
+    }
+
+    pub fn add_edge(&mut self, from: NodeId, to: NodeId) {
+        let neighbours = &mut self.adjacency[from];
+        if let Err(position) = neighbours.binary_search(&to) {
+            neighbours.insert(position, to);
+        }
+    }
+
+    pub fn add_undirected_edge(&mut self, a: NodeId, b: NodeId) {
+        self.add_edge(a, b);
+        self.add_edge(b, a);
+    }
+
+    pub fn node_id(&self, name: &str) -> Option<NodeId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: NodeId) -> &str {
+        &self.names[id]
+    }
+
+    pub fn names(&self, path: &[NodeId]) -> Vec<String> {
+        path.iter().map(|id| self.name(*id).to_string()).collect()
+    }
+
+    pub fn attributes(&self, id: NodeId) -> &T {
+        &self.attributes[id]
+    }
+
+    pub fn attributes_mut(&mut self, id: NodeId) -> &mut T {
+        &mut self.attributes[id]
+    }
+
+    // Neighbours are kept sorted by id, so traversal order is deterministic.
+    pub fn neighbours(&self, id: NodeId) -> &[NodeId] {
+        &self.adjacency[id]
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = NodeId> {
+        0..self.node_count()
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = (NodeId, NodeId)> + '_ {
+        self.adjacency
+            .iter()
+            .enumerate()
+            .flat_map(|(from, neighbours)| neighbours.iter().map(move |to| (from, *to)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interning() {
+        let mut graph = Graph::new();
+        let a = graph.add_node("a", 1);
+        let b = graph.add_node("b", 2);
+        let a_again = graph.add_node("a", 3);
+
+        assert_eq!(a, a_again);
+        assert_ne!(a, b);
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.node_id("b"), Some(b));
+        assert_eq!(graph.node_id("c"), None);
+        assert_eq!(graph.name(b), "b");
+        assert_eq!(*graph.attributes(a), 1);
+
+        *graph.attributes_mut(a) = 5;
+        assert_eq!(*graph.attributes(a), 5);
+    }
+
+    #[test]
+    fn test_edges() {
+        let mut graph = Graph::new();
+        let a = graph.add_node("a", ());
+        let b = graph.add_node("b", ());
+        let c = graph.add_node("c", ());
+        graph.add_undirected_edge(a, c);
+        graph.add_undirected_edge(a, b);
+        graph.add_undirected_edge(b, a);
+        graph.add_edge(c, b);
+
+        assert_eq!(graph.neighbours(a), &[b, c]);
+        assert_eq!(graph.neighbours(b), &[a]);
+        assert_eq!(graph.neighbours(c), &[a, b]);
+        assert_eq!(graph.edges().count(), 5);
+        assert_eq!(graph.names(&[c, a, b]), vec!["c", "a", "b"]);
+    }
+}
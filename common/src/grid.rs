@@ -0,0 +1,399 @@
+use std::{
+    error::Error,
+    fmt,
+    ops::{Index, IndexMut},
+    str::FromStr,
+};
+
+// Coordinates follow the `data[x][y]` convention of the puzzle solutions: `x`
+// selects the row (input line), `y` the column.
+pub type Coordinates = (usize, usize);
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Neighbourhood {
+    Four,
+    Eight,
+}
+
+impl Neighbourhood {
+    const FOUR: [(isize, isize); 4] = [(-1, 0), (0, -1), (1, 0), (0, 1)];
+    const EIGHT: [(isize, isize); 8] = [
+        (-1, -1),
+        (-1, 0),
+        (-1, 1),
+        (0, -1),
+        (0, 1),
+        (1, -1),
+        (1, 0),
+        (1, 1),
+    ];
+
+    pub fn offsets(self) -> &'static [(isize, isize)] {
+        match self {
+            Neighbourhood::Four => &Self::FOUR,
+            Neighbourhood::Eight => &Self::EIGHT,
+        }
+    }
+
+    // Neighbours of `(x, y)` which lie inside a grid of `size = (x_size, y_size)`.
+    pub fn neighbours(
+        self,
+        (x, y): Coordinates,
+        (x_size, y_size): (usize, usize),
+    ) -> impl Iterator<Item = Coordinates> {
+        self.offsets().iter().filter_map(move |(dx, dy)| {
+            let x = x.checked_add_signed(*dx)?;
+            let y = y.checked_add_signed(*dy)?;
+            (x < x_size && y < y_size).then_some((x, y))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseGridError {
+    InvalidCharacter {
+        x: usize,
+        y: usize,
+        character: char,
+    },
+    RaggedRow {
+        x: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for ParseGridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseGridError::InvalidCharacter { x, y, character } => write!(
+                f,
+                "line {}, column {}: unexpected character '{}'",
+                x + 1,
+                y + 1,
+                character
+            ),
+            ParseGridError::RaggedRow { x, expected, found } => write!(
+                f,
+                "line {}: expected {} cells, found {}",
+                x + 1,
+                expected,
+                found
+            ),
+        }
+    }
+}
+
+impl Error for ParseGridError {}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseCoordinatesError(pub String);
+
+impl fmt::Display for ParseCoordinatesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected <x>,<y>, got {}", self.0)
+    }
+}
+
+impl Error for ParseCoordinatesError {}
+
+// `x,y`, for grid `Coordinates` as well as for signed coordinates.
+pub fn parse_coordinates<T: FromStr>(coordinates: &str) -> Result<(T, T), ParseCoordinatesError> {
+    let invalid = || ParseCoordinatesError(coordinates.to_string());
+    let (x, y) = coordinates.split_once(',').ok_or_else(invalid)?;
+    let parse = |value: &str| value.trim().parse().map_err(|_| invalid());
+    Ok((parse(x)?, parse(y)?))
+}
+
+// Rectangular grid stored row by row in a single vector. `height` is the
+// number of rows, so valid coordinates are `(x, y)` with `x < height` and
+// `y < width`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(width: usize, height: usize, value: T) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            width,
+            height,
+            cells: vec![value; width * height],
+        }
+    }
+
+    pub fn from_fn<F>(width: usize, height: usize, mut f: F) -> Self
+    where
+        F: FnMut(Coordinates) -> T,
+    {
+        let cells = (0..height)
+            .flat_map(|x| (0..width).map(move |y| (x, y)))
+            .map(&mut f)
+            .collect();
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Self, ParseGridError> {
+        let height = rows.len();
+        let width = rows.first().map_or(0, Vec::len);
+        let mut cells = Vec::with_capacity(width * height);
+        for (x, row) in rows.into_iter().enumerate() {
+            if row.len() != width {
+                return Err(ParseGridError::RaggedRow {
+                    x,
+                    expected: width,
+                    found: row.len(),
+                });
+            }
+            cells.extend(row);
+        }
+        Ok(Self {
+            width,
+            height,
+            cells,
+        })
+    }
+
+    // One row per line, `parse_cell` returning `None` for invalid characters.
+    pub fn parse_chars<F>(input: &str, parse_cell: F) -> Result<Self, ParseGridError>
+    where
+        F: Fn(char) -> Option<T>,
+    {
+        let rows = input
+            .lines()
+            .enumerate()
+            .map(|(x, line)| {
+                line.chars()
+                    .enumerate()
+                    .map(|(y, character)| {
+                        parse_cell(character).ok_or(ParseGridError::InvalidCharacter {
+                            x,
+                            y,
+                            character,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_rows(rows)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // `(height, width)`, the exclusive bounds of `(x, y)`.
+    pub fn size(&self) -> (usize, usize) {
+        (self.height, self.width)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn contains(&self, (x, y): Coordinates) -> bool {
+        x < self.height && y < self.width
+    }
+
+    pub fn get(&self, coordinates: Coordinates) -> Option<&T> {
+        self.index_of(coordinates).map(|index| &self.cells[index])
+    }
+
+    pub fn get_mut(&mut self, coordinates: Coordinates) -> Option<&mut T> {
+        self.index_of(coordinates)
+            .map(move |index| &mut self.cells[index])
+    }
+
+    pub fn neighbours(
+        &self,
+        coordinates: Coordinates,
+        neighbourhood: Neighbourhood,
+    ) -> impl Iterator<Item = Coordinates> {
+        neighbourhood.neighbours(coordinates, self.size())
+    }
+
+    // Every coordinate, row by row.
+    pub fn coordinates(&self) -> impl Iterator<Item = Coordinates> {
+        let width = self.width;
+        (0..self.height).flat_map(move |x| (0..width).map(move |y| (x, y)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.cells.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.cells.iter_mut()
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (Coordinates, &T)> {
+        self.coordinates().zip(self.cells.iter())
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    pub fn map<U, F>(&self, f: F) -> Grid<U>
+    where
+        F: FnMut(&T) -> U,
+    {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+
+    fn index_of(&self, (x, y): Coordinates) -> Option<usize> {
+        self.contains((x, y)).then(|| x * self.width + y)
+    }
+}
+
+impl Grid<u8> {
+    pub fn parse_digits(input: &str) -> Result<Self, ParseGridError> {
+        Self::parse_chars(input, |c| c.to_digit(10).map(|digit| digit as u8))
+    }
+}
+
+impl<T> Index<Coordinates> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, coordinates: Coordinates) -> &Self::Output {
+        match self.index_of(coordinates) {
+            Some(index) => &self.cells[index],
+            None => panic!(
+                "Coordinates {:?} out of grid of size {:?}",
+                coordinates,
+                self.size()
+            ),
+        }
+    }
+}
+
+impl<T> IndexMut<Coordinates> for Grid<T> {
+    fn index_mut(&mut self, coordinates: Coordinates) -> &mut Self::Output {
+        match self.index_of(coordinates) {
+            Some(index) => &mut self.cells[index],
+            None => panic!(
+                "Coordinates {:?} out of grid of size {:?}",
+                coordinates,
+                self.size()
+            ),
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (x, row) in self.rows().enumerate() {
+            if x > 0 {
+                writeln!(f)?;
+            }
+            for cell in row {
+                write!(f, "{}", cell)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let grid = Grid::parse_digits("123\n456\n").unwrap();
+
+        assert_eq!(grid.size(), (2, 3));
+        assert_eq!(grid[(1, 0)], 4);
+        assert_eq!(grid.get((0, 3)), None);
+        assert_eq!(grid.to_string(), "123\n456");
+        assert_eq!(
+            grid.rows().collect::<Vec<_>>(),
+            vec![&[1, 2, 3][..], &[4, 5, 6][..]]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Grid::parse_digits("12\n3x"),
+            Err(ParseGridError::InvalidCharacter {
+                x: 1,
+                y: 1,
+                character: 'x'
+            })
+        );
+        let error = Grid::parse_digits("12\n345").unwrap_err();
+        assert_eq!(
+            error,
+            ParseGridError::RaggedRow {
+                x: 1,
+                expected: 2,
+                found: 3
+            }
+        );
+        assert_eq!(error.to_string(), "line 2: expected 2 cells, found 3");
+    }
+
+    #[test]
+    fn test_parse_coordinates() {
+        assert_eq!(parse_coordinates::<usize>("3,14"), Ok((3, 14)));
+        assert_eq!(parse_coordinates::<isize>("-2, 5"), Ok((-2, 5)));
+        assert_eq!(
+            parse_coordinates::<usize>("-2,5"),
+            Err(ParseCoordinatesError("-2,5".to_string()))
+        );
+        assert_eq!(
+            parse_coordinates::<usize>("3;14").unwrap_err().to_string(),
+            "expected <x>,<y>, got 3;14"
+        );
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let grid = Grid::new(3, 2, 0);
+
+        let mut four = grid
+            .neighbours((0, 0), Neighbourhood::Four)
+            .collect::<Vec<_>>();
+        four.sort();
+        assert_eq!(four, vec![(0, 1), (1, 0)]);
+        assert_eq!(grid.neighbours((1, 2), Neighbourhood::Eight).count(), 3);
+        assert_eq!(
+            Grid::new(3, 3, 0)
+                .neighbours((1, 1), Neighbourhood::Eight)
+                .count(),
+            8
+        );
+    }
+
+    #[test]
+    fn test_from_fn_and_map() {
+        let grid = Grid::from_fn(3, 2, |(x, y)| x * 10 + y);
+
+        assert_eq!(grid.to_string(), "012\n101112");
+        assert_eq!(grid.cells().last(), Some(((1, 2), &12)));
+        let doubled = grid.map(|cell| cell * 2);
+        assert_eq!(doubled[(1, 1)], 22);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod automaton;
+pub mod graph;
+pub mod grid;
+pub mod pathfinding;
+pub mod visualisation;
@@ -0,0 +1,580 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashSet, VecDeque},
+};
+
+pub use crate::grid::{Coordinates, Neighbourhood};
+
+use crate::grid::Grid;
+
+pub trait WeightedGrid {
+    fn size(&self) -> (usize, usize);
+
+    // Cost of entering the cell, `None` for cells which can't be entered.
+    fn cost(&self, coordinates: Coordinates) -> Option<usize>;
+}
+
+impl<T: Copy + Into<usize>> WeightedGrid for Grid<T> {
+    fn size(&self) -> (usize, usize) {
+        Grid::size(self)
+    }
+
+    fn cost(&self, coordinates: Coordinates) -> Option<usize> {
+        self.get(coordinates).map(|cost| (*cost).into())
+    }
+}
+
+pub fn manhattan_distance(a: Coordinates, b: Coordinates) -> usize {
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+}
+
+pub fn chebyshev_distance(a: Coordinates, b: Coordinates) -> usize {
+    a.0.abs_diff(b.0).max(a.1.abs_diff(b.1))
+}
+
+pub fn no_heuristic(_: Coordinates, _: Coordinates) -> usize {
+    0
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Path {
+    pub cost: usize,
+    pub cells: Vec<Coordinates>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct Node {
+    estimate: usize,
+    cost: usize,
+    coordinates: Coordinates,
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .cmp(&self.estimate)
+            .then_with(|| self.cost.cmp(&other.cost))
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// The heuristic has to be admissible for the result to be optimal: it may never
+// estimate more than the lowest cost of getting from a cell to the goal.
+// Manhattan distance for the four-neighbourhood and Chebyshev distance for the
+// eight-neighbourhood are admissible when no cell costs less than 1. On grids
+// with cheaper cells they have to be scaled by the lowest cost, which for cells
+// costing 0 leaves `dijkstra`.
+pub fn a_star<Map, Heuristic>(
+    grid: &Map,
+    start: Coordinates,
+    goal: Coordinates,
+    neighbourhood: Neighbourhood,
+    heuristic: Heuristic,
+) -> Option<Path>
+where
+    Map: WeightedGrid + ?Sized,
+    Heuristic: Fn(Coordinates, Coordinates) -> usize,
+{
+    search(grid, start, goal, neighbourhood, heuristic, |_, _| false)
+}
+
+// A* which additionally never takes a step for which `blocked(from, to)` holds.
+fn search<Map, Heuristic, Blocked>(
+    grid: &Map,
+    start: Coordinates,
+    goal: Coordinates,
+    neighbourhood: Neighbourhood,
+    heuristic: Heuristic,
+    blocked: Blocked,
+) -> Option<Path>
+where
+    Map: WeightedGrid + ?Sized,
+    Heuristic: Fn(Coordinates, Coordinates) -> usize,
+    Blocked: Fn(Coordinates, Coordinates) -> bool,
+{
+    let size = grid.size();
+    if start.0 >= size.0 || start.1 >= size.1 || goal.0 >= size.0 || goal.1 >= size.1 {
+        return None;
+    }
+    let index = |(x, y): Coordinates| x * size.1 + y;
+
+    let mut cost = vec![usize::MAX; size.0 * size.1];
+    let mut previous = vec![None; size.0 * size.1];
+    let mut remaining_nodes = BinaryHeap::new();
+    cost[index(start)] = 0;
+    remaining_nodes.push(Node {
+        estimate: heuristic(start, goal),
+        cost: 0,
+        coordinates: start,
+    });
+
+    while let Some(Node {
+        cost: node_cost,
+        coordinates,
+        ..
+    }) = remaining_nodes.pop()
+    {
+        if coordinates == goal {
+            let mut cells = vec![goal];
+            while let Some(cell) = previous[index(*cells.last().unwrap())] {
+                cells.push(cell);
+            }
+            cells.reverse();
+            return Some(Path {
+                cost: node_cost,
+                cells,
+            });
+        }
+
+        if node_cost > cost[index(coordinates)] {
+            continue;
+        }
+
+        for adjacent in neighbourhood.neighbours(coordinates, size) {
+            if blocked(coordinates, adjacent) {
+                continue;
+            }
+            let adjacent_cost = match grid.cost(adjacent) {
+                Some(adjacent_cost) => node_cost + adjacent_cost,
+                None => continue,
+            };
+            if adjacent_cost < cost[index(adjacent)] {
+                cost[index(adjacent)] = adjacent_cost;
+                previous[index(adjacent)] = Some(coordinates);
+                remaining_nodes.push(Node {
+                    estimate: adjacent_cost + heuristic(adjacent, goal),
+                    cost: adjacent_cost,
+                    coordinates: adjacent,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+pub fn dijkstra<Map>(
+    grid: &Map,
+    start: Coordinates,
+    goal: Coordinates,
+    neighbourhood: Neighbourhood,
+) -> Option<Path>
+where
+    Map: WeightedGrid + ?Sized,
+{
+    a_star(grid, start, goal, neighbourhood, no_heuristic)
+}
+
+// Lowest total cost of reaching every cell from `source`, `None` for cells
+// which can't be reached.
+pub fn distance_field<Map>(
+    grid: &Map,
+    source: Coordinates,
+    neighbourhood: Neighbourhood,
+) -> Grid<Option<usize>>
+where
+    Map: WeightedGrid + ?Sized,
+{
+    let size = grid.size();
+    let mut distances = Grid::new(size.1, size.0, None);
+    if !distances.contains(source) {
+        return distances;
+    }
+
+    let mut remaining_nodes = BinaryHeap::new();
+    distances[source] = Some(0);
+    remaining_nodes.push(Node {
+        estimate: 0,
+        cost: 0,
+        coordinates: source,
+    });
+    while let Some(Node {
+        cost, coordinates, ..
+    }) = remaining_nodes.pop()
+    {
+        if Some(cost) > distances[coordinates] {
+            continue;
+        }
+        for adjacent in neighbourhood.neighbours(coordinates, size) {
+            let adjacent_cost = match grid.cost(adjacent) {
+                Some(adjacent_cost) => cost + adjacent_cost,
+                None => continue,
+            };
+            let distance = &mut distances[adjacent];
+            if distance.is_none_or(|distance| adjacent_cost < distance) {
+                *distance = Some(adjacent_cost);
+                remaining_nodes.push(Node {
+                    estimate: adjacent_cost,
+                    cost: adjacent_cost,
+                    coordinates: adjacent,
+                });
+            }
+        }
+    }
+    distances
+}
+
+// Breadth-first search over a grid of `size`, starting from all `sources` at
+// once. Whenever a neighbour of a searched cell is reached, `enter` decides
+// whether the search continues from it, so it has to return `false` for cells
+// it has already let in. Returns the searched cells in the order they were
+// searched from, sources first.
+pub fn flood_fill<Sources, Enter>(
+    size: (usize, usize),
+    sources: Sources,
+    neighbourhood: Neighbourhood,
+    mut enter: Enter,
+) -> Vec<Coordinates>
+where
+    Sources: IntoIterator<Item = Coordinates>,
+    Enter: FnMut(Coordinates) -> bool,
+{
+    let mut to_check = sources.into_iter().collect::<VecDeque<_>>();
+    let mut searched = Vec::with_capacity(to_check.len());
+    while let Some(front) = to_check.pop_front() {
+        searched.push(front);
+        for adjacent in neighbourhood.neighbours(front, size) {
+            if enter(adjacent) {
+                to_check.push_back(adjacent);
+            }
+        }
+    }
+    searched
+}
+
+// Up to `k` loopless paths from `start` to `goal` in order of increasing cost,
+// found with Yen's algorithm. The first path is the one `dijkstra` finds, every
+// later one the cheapest candidate found so far, ties going to fewer cells and
+// then to the lexicographically smaller cells. Candidates turn up as paths are
+// picked, so equal-cost paths after the first aren't sorted overall, but the
+// order is always the same for the same grid.
+pub fn k_shortest_paths<Map>(
+    grid: &Map,
+    start: Coordinates,
+    goal: Coordinates,
+    neighbourhood: Neighbourhood,
+    k: usize,
+) -> Vec<Path>
+where
+    Map: WeightedGrid + ?Sized,
+{
+    let mut paths: Vec<Path> = Vec::new();
+    if k == 0 {
+        return paths;
+    }
+    match dijkstra(grid, start, goal, neighbourhood) {
+        Some(path) => paths.push(path),
+        None => return paths,
+    }
+
+    let mut candidates = BinaryHeap::new();
+    let mut seen = HashSet::new();
+    seen.insert(paths[0].cells.clone());
+    while paths.len() < k {
+        let last = paths.last().unwrap().cells.clone();
+        let mut root_cost = 0;
+        for spur_index in 0..last.len() - 1 {
+            let spur = last[spur_index];
+            if spur_index > 0 {
+                root_cost += grid.cost(spur).unwrap();
+            }
+            let root = &last[..=spur_index];
+            // Paths sharing the root may not leave the spur the same way, and
+            // the rest of the path may not return to the root.
+            let used_steps = paths
+                .iter()
+                .filter(|path| {
+                    path.cells.len() > spur_index + 1 && path.cells[..=spur_index] == *root
+                })
+                .map(|path| path.cells[spur_index + 1])
+                .collect::<HashSet<_>>();
+            let root_cells = root[..spur_index].iter().collect::<HashSet<_>>();
+            let spur_path = search(grid, spur, goal, neighbourhood, no_heuristic, |from, to| {
+                root_cells.contains(&to) || (from == spur && used_steps.contains(&to))
+            });
+
+            if let Some(spur_path) = spur_path {
+                let mut cells = root[..spur_index].to_vec();
+                cells.extend(spur_path.cells);
+                if seen.insert(cells.clone()) {
+                    candidates.push(Candidate(Path {
+                        cost: root_cost + spur_path.cost,
+                        cells,
+                    }));
+                }
+            }
+        }
+
+        match candidates.pop() {
+            Some(Candidate(path)) => paths.push(path),
+            None => break,
+        }
+    }
+    paths
+}
+
+// Ordered for the max-heap to pop the cheapest candidate first, then the one
+// with the fewest cells, then the one with the lexicographically smallest cells.
+#[derive(Eq, PartialEq)]
+struct Candidate(Path);
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .0
+            .cost
+            .cmp(&self.0.cost)
+            .then_with(|| other.0.cells.len().cmp(&self.0.cells.len()))
+            .then_with(|| other.0.cells.cmp(&self.0.cells))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&str]) -> Grid<u8> {
+        Grid::parse_digits(&rows.join("\n")).unwrap()
+    }
+
+    #[test]
+    fn test_rectangular_grid_path() {
+        let risks = grid(&["1163", "1381", "2136"]);
+
+        let path = a_star(
+            &risks,
+            (0, 0),
+            (2, 3),
+            Neighbourhood::Four,
+            manhattan_distance,
+        )
+        .unwrap();
+
+        assert_eq!(path.cost, 13);
+        assert_eq!(path.cells.first(), Some(&(0, 0)));
+        assert_eq!(path.cells.last(), Some(&(2, 3)));
+        let cells_cost = path.cells[1..]
+            .iter()
+            .map(|cell| risks.cost(*cell).unwrap())
+            .sum::<usize>();
+        assert_eq!(cells_cost, path.cost);
+        assert!(path
+            .cells
+            .windows(2)
+            .all(|step| manhattan_distance(step[0], step[1]) == 1));
+        assert_eq!(
+            dijkstra(&risks, (0, 0), (2, 3), Neighbourhood::Four)
+                .unwrap()
+                .cost,
+            path.cost
+        );
+    }
+
+    #[test]
+    fn test_zero_cost_cells() {
+        // Winding around the nines is free, going straight along the bottom
+        // costs 1.
+        let costs = grid(&["09000", "09090", "00010"]);
+        let min_cost = *costs.iter().min().unwrap() as usize;
+
+        let expected = dijkstra(&costs, (0, 0), (2, 4), Neighbourhood::Four).unwrap();
+        let scaled = a_star(&costs, (0, 0), (2, 4), Neighbourhood::Four, |a, b| {
+            manhattan_distance(a, b) * min_cost
+        });
+        let unscaled = a_star(
+            &costs,
+            (0, 0),
+            (2, 4),
+            Neighbourhood::Four,
+            manhattan_distance,
+        );
+
+        assert_eq!(expected.cost, 0);
+        assert_eq!(scaled, Some(expected));
+        assert_eq!(unscaled.map(|path| path.cost), Some(1));
+    }
+
+    #[test]
+    fn test_eight_neighbourhood() {
+        let risks = grid(&["199", "919", "991"]);
+
+        let four = dijkstra(&risks, (0, 0), (2, 2), Neighbourhood::Four).unwrap();
+        let eight = a_star(
+            &risks,
+            (0, 0),
+            (2, 2),
+            Neighbourhood::Eight,
+            chebyshev_distance,
+        )
+        .unwrap();
+
+        assert_eq!(four.cost, 1 + 9 + 9 + 1);
+        assert_eq!(eight.cost, 2);
+        assert_eq!(eight.cells, vec![(0, 0), (1, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn test_unreachable_goal() {
+        struct Walled;
+
+        impl WeightedGrid for Walled {
+            fn size(&self) -> (usize, usize) {
+                (3, 3)
+            }
+
+            fn cost(&self, (x, _): Coordinates) -> Option<usize> {
+                (x != 1).then_some(1)
+            }
+        }
+
+        assert_eq!(
+            dijkstra(&Walled, (0, 0), (2, 2), Neighbourhood::Eight),
+            None
+        );
+        assert_eq!(dijkstra(&Walled, (0, 0), (5, 5), Neighbourhood::Four), None);
+        assert_eq!(
+            dijkstra(&Walled, (0, 0), (0, 2), Neighbourhood::Four).map(|path| path.cost),
+            Some(2)
+        );
+    }
+
+    fn simple_path_costs(
+        grid: &Grid<u8>,
+        path: &mut Vec<Coordinates>,
+        goal: Coordinates,
+        costs: &mut Vec<usize>,
+    ) {
+        let cell = *path.last().unwrap();
+        if cell == goal {
+            costs.push(path[1..].iter().map(|cell| grid[*cell] as usize).sum());
+            return;
+        }
+        for adjacent in grid.neighbours(cell, Neighbourhood::Four) {
+            if !path.contains(&adjacent) {
+                path.push(adjacent);
+                simple_path_costs(grid, path, goal, costs);
+                path.pop();
+            }
+        }
+    }
+
+    #[test]
+    fn test_k_shortest_paths() {
+        let risks = grid(&["116", "138", "213"]);
+        let mut costs = Vec::new();
+        simple_path_costs(&risks, &mut vec![(0, 0)], (2, 2), &mut costs);
+        costs.sort_unstable();
+
+        let paths = k_shortest_paths(&risks, (0, 0), (2, 2), Neighbourhood::Four, 6);
+
+        assert_eq!(
+            paths.iter().map(|path| path.cost).collect::<Vec<_>>(),
+            costs[..6]
+        );
+        let distinct = paths
+            .iter()
+            .map(|path| path.cells.clone())
+            .collect::<HashSet<_>>();
+        assert_eq!(distinct.len(), paths.len());
+        for path in &paths {
+            let cells = path.cells.iter().collect::<HashSet<_>>();
+            assert_eq!(cells.len(), path.cells.len());
+            let cells_cost = path.cells[1..]
+                .iter()
+                .map(|cell| risks.cost(*cell).unwrap())
+                .sum::<usize>();
+            assert_eq!(cells_cost, path.cost);
+        }
+    }
+
+    #[test]
+    fn test_k_shortest_paths_tie_break() {
+        let risks = grid(&["111", "111"]);
+
+        let paths = k_shortest_paths(&risks, (0, 0), (1, 2), Neighbourhood::Four, 4);
+
+        assert_eq!(
+            paths
+                .iter()
+                .map(|path| (path.cost, path.cells.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (3, vec![(0, 0), (1, 0), (1, 1), (1, 2)]),
+                (3, vec![(0, 0), (0, 1), (1, 1), (1, 2)]),
+                (3, vec![(0, 0), (0, 1), (0, 2), (1, 2)]),
+                (5, vec![(0, 0), (1, 0), (1, 1), (0, 1), (0, 2), (1, 2)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_k_shortest_paths_exhausted() {
+        let risks = grid(&["12", "34"]);
+
+        let paths = k_shortest_paths(&risks, (0, 1), (1, 0), Neighbourhood::Four, 5);
+
+        assert_eq!(
+            paths.iter().map(|path| path.cost).collect::<Vec<_>>(),
+            vec![4, 7]
+        );
+        assert_eq!(
+            k_shortest_paths(&risks, (0, 0), (0, 0), Neighbourhood::Four, 3).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_flood_fill() {
+        let heights = grid(&["1191", "9991", "1111"]);
+        let mut entered = heights.map(|_| false);
+        entered[(0, 0)] = true;
+
+        let region = flood_fill(heights.size(), [(0, 0)], Neighbourhood::Four, |cell| {
+            let enter = heights[cell] < 9 && !entered[cell];
+            entered[cell] = true;
+            enter
+        });
+
+        assert_eq!(region, vec![(0, 0), (0, 1)]);
+        assert_eq!(
+            flood_fill(
+                heights.size(),
+                [(2, 0), (0, 3)],
+                Neighbourhood::Eight,
+                |_| false
+            ),
+            vec![(2, 0), (0, 3)]
+        );
+    }
+
+    #[test]
+    fn test_distance_field() {
+        let risks = grid(&["1163", "1381", "2136"]);
+
+        let distances = distance_field(&risks, (0, 0), Neighbourhood::Four);
+
+        assert_eq!(
+            distances.rows().next().unwrap(),
+            [Some(0), Some(1), Some(7), Some(10)]
+        );
+        assert_eq!(distances[(2, 3)], Some(13));
+        for (coordinates, distance) in distances.cells() {
+            assert_eq!(
+                *distance,
+                dijkstra(&risks, (0, 0), coordinates, Neighbourhood::Four).map(|path| path.cost)
+            );
+        }
+    }
+}
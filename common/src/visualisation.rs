@@ -0,0 +1,388 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, StdoutLock, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use crate::grid::Coordinates;
+
+// A single frame of a grid simulation. Coordinates follow the grid convention,
+// `x` being the row.
+pub trait Render {
+    fn size(&self) -> (usize, usize);
+
+    fn glyph(&self, coordinates: Coordinates) -> char;
+
+    fn colour(&self, coordinates: Coordinates) -> [u8; 3];
+}
+
+// Redraws frames in place using ANSI escape codes, waiting between frames to
+// keep the requested frame rate.
+pub struct TerminalAnimation<W: Write> {
+    writer: W,
+    frame_delay: Duration,
+    frames: usize,
+}
+
+impl<W: Write> TerminalAnimation<W> {
+    // A frame rate of 0 draws frames as fast as possible. Rates too low for
+    // the delay to fit in a `Duration` wait as long as one can.
+    pub fn new(writer: W, frames_per_second: f64) -> Self {
+        let frame_delay = if frames_per_second > 0.0 {
+            Duration::try_from_secs_f64(1.0 / frames_per_second).unwrap_or(Duration::MAX)
+        } else {
+            Duration::ZERO
+        };
+        Self {
+            writer,
+            frame_delay,
+            frames: 0,
+        }
+    }
+
+    pub fn draw<Frame: Render + ?Sized>(&mut self, frame: &Frame, caption: &str) -> io::Result<()> {
+        if self.frames == 0 {
+            // Clear the screen and hide the cursor.
+            write!(self.writer, "\x1b[2J\x1b[?25l")?;
+        } else {
+            thread::sleep(self.frame_delay);
+        }
+        write!(self.writer, "\x1b[H")?;
+
+        let (x_size, y_size) = frame.size();
+        for x in 0..x_size {
+            let mut previous_colour = None;
+            for y in 0..y_size {
+                let colour = frame.colour((x, y));
+                if previous_colour != Some(colour) {
+                    let [r, g, b] = colour;
+                    write!(self.writer, "\x1b[38;2;{};{};{}m", r, g, b)?;
+                    previous_colour = Some(colour);
+                }
+                write!(self.writer, "{}", frame.glyph((x, y)))?;
+            }
+            writeln!(self.writer, "\x1b[0m\x1b[K")?;
+        }
+        writeln!(self.writer, "{}\x1b[K", caption)?;
+        self.writer.flush()?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    // Shows the cursor again and returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        write!(self.writer, "\x1b[0m\x1b[?25h")?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+// Binary PPM with every cell drawn as a `scale` x `scale` square.
+pub fn write_ppm<Frame: Render + ?Sized, W: Write>(
+    frame: &Frame,
+    scale: usize,
+    writer: &mut W,
+) -> io::Result<()> {
+    let (x_size, y_size) = frame.size();
+    writeln!(writer, "P6")?;
+    writeln!(writer, "{} {}", y_size * scale, x_size * scale)?;
+    writeln!(writer, "255")?;
+    for x in 0..x_size {
+        let row = (0..y_size)
+            .flat_map(|y| std::iter::repeat_n(frame.colour((x, y)), scale).flatten())
+            .collect::<Vec<u8>>();
+        for _ in 0..scale {
+            writer.write_all(&row)?;
+        }
+    }
+    Ok(())
+}
+
+// Writes every frame to `frame_00000.ppm`, `frame_00001.ppm`, ... in a
+// directory, ready to be assembled into a video with an external tool.
+pub struct FrameDumper {
+    directory: PathBuf,
+    scale: usize,
+    frames: usize,
+}
+
+impl FrameDumper {
+    pub fn new<P: AsRef<Path>>(directory: P, scale: usize) -> io::Result<Self> {
+        fs::create_dir_all(&directory)?;
+        Ok(Self {
+            directory: directory.as_ref().to_path_buf(),
+            scale,
+            frames: 0,
+        })
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    pub fn write<Frame: Render + ?Sized>(&mut self, frame: &Frame) -> io::Result<PathBuf> {
+        let path = self.directory.join(format!("frame_{:05}.ppm", self.frames));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        write_ppm(frame, self.scale, &mut writer)?;
+        writer.flush()?;
+        self.frames += 1;
+        Ok(path)
+    }
+}
+
+// Command line options of a visualised simulation: `--animate`, `--fps <n>`,
+// `--frames <directory>` and `--scale <n>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisualiserOptions {
+    pub animate: bool,
+    pub fps: f64,
+    // Directory frames are written to.
+    pub frames: Option<String>,
+    // Pixels per cell side in written frames.
+    pub scale: usize,
+}
+
+impl Default for VisualiserOptions {
+    fn default() -> Self {
+        Self {
+            animate: false,
+            fps: 10.0,
+            frames: None,
+            scale: 4,
+        }
+    }
+}
+
+impl VisualiserOptions {
+    // Takes `arg` and its value from `args` if it is a visualiser option,
+    // returns whether it was.
+    pub fn parse_arg<I: Iterator<Item = String>>(&mut self, arg: &str, args: &mut I) -> bool {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| panic!("Missing value for {}", arg))
+        };
+        match arg {
+            "--animate" => self.animate = true,
+            "--fps" => self.fps = parse_fps(&value()).unwrap_or_else(|error| panic!("{}", error)),
+            "--frames" => self.frames = Some(value()),
+            "--scale" => self.scale = value().parse().unwrap(),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn any(&self) -> bool {
+        self.animate || self.frames.is_some()
+    }
+}
+
+// Frame rates from one frame a minute up to a thousand a second, or 0 for as
+// fast as possible.
+const MIN_FPS: f64 = 1.0 / 60.0;
+const MAX_FPS: f64 = 1000.0;
+
+fn parse_fps(value: &str) -> Result<f64, String> {
+    let fps = value
+        .parse::<f64>()
+        .map_err(|error| format!("Invalid frame rate {}: {}", value, error))?;
+    if fps == 0.0 || (MIN_FPS..=MAX_FPS).contains(&fps) {
+        Ok(fps)
+    } else {
+        Err(format!(
+            "Frame rate {} has to be 0 or from 1/60 to {}",
+            value, MAX_FPS
+        ))
+    }
+}
+
+// Sends every frame to a terminal animation, a frame directory, or both.
+pub struct Visualiser<W: Write> {
+    animation: Option<TerminalAnimation<W>>,
+    dumper: Option<FrameDumper>,
+}
+
+impl Visualiser<StdoutLock<'static>> {
+    // Animates on the standard output.
+    pub fn from_options(options: &VisualiserOptions) -> io::Result<Self> {
+        let animation = options
+            .animate
+            .then(|| TerminalAnimation::new(io::stdout().lock(), options.fps));
+        let dumper = match &options.frames {
+            Some(directory) => {
+                Some(FrameDumper::new(directory, options.scale).map_err(|error| {
+                    io::Error::new(
+                        error.kind(),
+                        format!("Can't create directory {}: {}", directory, error),
+                    )
+                })?)
+            }
+            None => None,
+        };
+        Ok(Self::new(animation, dumper))
+    }
+}
+
+impl<W: Write> Visualiser<W> {
+    pub fn new(animation: Option<TerminalAnimation<W>>, dumper: Option<FrameDumper>) -> Self {
+        Self { animation, dumper }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.animation.is_some() || self.dumper.is_some()
+    }
+
+    pub fn show<Frame: Render + ?Sized>(&mut self, frame: &Frame, caption: &str) -> io::Result<()> {
+        if let Some(animation) = &mut self.animation {
+            animation.draw(frame, caption)?;
+        }
+        if let Some(dumper) = &mut self.dumper {
+            dumper.write(frame)?;
+        }
+        Ok(())
+    }
+
+    // Describes the frames written to the frame directory, if any.
+    pub fn finish(self) -> io::Result<Option<String>> {
+        if let Some(animation) = self.animation {
+            animation.finish()?;
+        }
+        Ok(self.dumper.map(|dumper| {
+            format!(
+                "Wrote {} frames to {}",
+                dumper.frames(),
+                dumper.directory.display()
+            )
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Checkerboard;
+
+    impl Render for Checkerboard {
+        fn size(&self) -> (usize, usize) {
+            (2, 3)
+        }
+
+        fn glyph(&self, (x, y): Coordinates) -> char {
+            if (x + y) % 2 == 0 {
+                '#'
+            } else {
+                '.'
+            }
+        }
+
+        fn colour(&self, (x, y): Coordinates) -> [u8; 3] {
+            if (x + y) % 2 == 0 {
+                [255, 255, 255]
+            } else {
+                [0, 0, 0]
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let mut output = Vec::new();
+
+        write_ppm(&Checkerboard, 2, &mut output).unwrap();
+
+        let header = b"P6\n6 4\n255\n";
+        assert!(output.starts_with(header));
+        let pixels = &output[header.len()..];
+        assert_eq!(pixels.len(), 6 * 4 * 3);
+        assert_eq!(
+            &pixels[..12],
+            &[255, 255, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(&pixels[18..30], &pixels[..12]);
+    }
+
+    #[test]
+    fn test_terminal_animation() {
+        let mut animation = TerminalAnimation::new(Vec::new(), 0.0);
+
+        animation.draw(&Checkerboard, "step 1").unwrap();
+        animation.draw(&Checkerboard, "step 2").unwrap();
+        let output = String::from_utf8(animation.finish().unwrap()).unwrap();
+
+        assert!(output.starts_with("\x1b[2J\x1b[?25l\x1b[H\x1b[38;2;255;255;255m#"));
+        assert_eq!(output.matches("\x1b[2J").count(), 1);
+        assert_eq!(output.matches("\x1b[H").count(), 2);
+        assert!(output.contains("step 2\x1b[K\n"));
+        assert!(output.ends_with("\x1b[?25h"));
+    }
+
+    #[test]
+    fn test_frame_dumper() {
+        let directory = std::env::temp_dir().join(format!("frames-{}", std::process::id()));
+        let mut dumper = FrameDumper::new(&directory, 1).unwrap();
+
+        let first = dumper.write(&Checkerboard).unwrap();
+        let second = dumper.write(&Checkerboard).unwrap();
+
+        assert_eq!(first.file_name().unwrap(), "frame_00000.ppm");
+        assert_eq!(second.file_name().unwrap(), "frame_00001.ppm");
+        assert_eq!(dumper.frames(), 2);
+        assert!(fs::read(&second).unwrap().starts_with(b"P6\n3 2\n"));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_visualiser() {
+        let mut visualiser = Visualiser::new(Some(TerminalAnimation::new(Vec::new(), 0.0)), None);
+
+        assert!(visualiser.is_active());
+        visualiser.show(&Checkerboard, "").unwrap();
+        assert_eq!(visualiser.finish().unwrap(), None);
+        assert!(!Visualiser::<Vec<u8>>::new(None, None).is_active());
+    }
+
+    #[test]
+    fn test_visualiser_options() {
+        let mut options = VisualiserOptions {
+            scale: 8,
+            ..Default::default()
+        };
+        let mut args = ["--fps", "30", "--steps", "5", "--frames", "out"]
+            .into_iter()
+            .map(String::from);
+
+        let mut unknown = Vec::new();
+        while let Some(arg) = args.next() {
+            if !options.parse_arg(&arg, &mut args) {
+                unknown.push(arg);
+            }
+        }
+
+        assert_eq!(unknown, ["--steps", "5"]);
+        assert_eq!(
+            options,
+            VisualiserOptions {
+                animate: false,
+                fps: 30.0,
+                frames: Some("out".to_string()),
+                scale: 8,
+            }
+        );
+        assert!(options.any());
+        assert!(!VisualiserOptions::default().any());
+    }
+
+    #[test]
+    fn test_parse_fps() {
+        assert_eq!(parse_fps("0"), Ok(0.0));
+        assert_eq!(parse_fps("2.5"), Ok(2.5));
+        assert_eq!(parse_fps("1000"), Ok(1000.0));
+        for invalid in ["NaN", "inf", "-1", "1e-300", "1001", "fast"] {
+            assert!(parse_fps(invalid).is_err(), "{}", invalid);
+        }
+        let animation = TerminalAnimation::new(Vec::new(), 1e-300);
+        assert_eq!(animation.frame_delay, Duration::MAX);
+    }
+}
@@ -1,126 +1,78 @@
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
+use std::{env, fs};
+
+use common::{
+    grid::{Coordinates, Grid, Neighbourhood},
+    pathfinding::flood_fill,
+    visualisation::{Visualiser, VisualiserOptions},
 };
 
-type Octopuses = Vec<Vec<u32>>;
+mod simulator;
 
-const ALREADY_FLASHED_VALUE: u32 = std::u32::MAX;
-const TO_FLASH_THRESHOLD_VALUE: u32 = 10;
+use simulator::{Simulator, Snapshot};
 
-fn load_data(file_name: &str) -> Octopuses {
-    let file = File::open(file_name).expect(&format!("Can't read file {}", file_name));
-    let file = BufReader::new(file);
-    file.lines()
-        .map(|line| {
-            line.unwrap()
-                .chars()
-                .map(|string_digit| string_digit.to_digit(10).unwrap() as u32)
-                .collect()
-        })
-        .collect()
-}
+type Octopuses = Grid<u32>;
 
-fn increase_energy_level_by_one(octopuses: Octopuses) -> Octopuses {
-    octopuses
-        .into_iter()
-        .map(|octopuses_row| {
-            octopuses_row
-                .into_iter()
-                .map(|octopus| octopus + 1)
-                .collect()
-        })
-        .collect()
-}
-
-fn valid_coordinates(coordinates_to_check: (i32, i32), valid_size: (i32, i32)) -> bool {
-    coordinates_to_check.0 >= 0
-        && coordinates_to_check.1 >= 0
-        && coordinates_to_check.0 < valid_size.0 as i32
-        && coordinates_to_check.1 < valid_size.1 as i32
-}
+const TO_FLASH_THRESHOLD_VALUE: u32 = 10;
 
-fn recurent_flash(current_coordinates: (usize, usize), octopuses: &mut Octopuses) {
-    let (current_x, current_y) = current_coordinates;
-
-    if octopuses[current_x][current_y] >= TO_FLASH_THRESHOLD_VALUE
-        && octopuses[current_x][current_y] != ALREADY_FLASHED_VALUE
-    {
-        octopuses[current_x][current_y] = ALREADY_FLASHED_VALUE;
-        for x in 0..=2 {
-            for y in 0..=2 {
-                let x_to_check = (current_x + x) as i32 - 1;
-                let y_to_check = (current_y + y) as i32 - 1;
-
-                if valid_coordinates(
-                    (x_to_check, y_to_check),
-                    (octopuses.len() as i32, octopuses.len() as i32),
-                ) && (x_to_check != current_x as i32 || y_to_check != current_y as i32)
-                {
-                    let x_to_check = x_to_check as usize;
-                    let y_to_check = y_to_check as usize;
-                    if octopuses[x_to_check][y_to_check] != ALREADY_FLASHED_VALUE {
-                        octopuses[x_to_check][y_to_check] += 1;
-                        recurent_flash((x_to_check, y_to_check), octopuses);
-                    }
-                }
-            }
-        }
-    }
+fn load_data(file_name: &str) -> Octopuses {
+    let input =
+        fs::read_to_string(file_name).unwrap_or_else(|_| panic!("Can't read file {}", file_name));
+    Grid::parse_chars(&input, |string_digit| string_digit.to_digit(10))
+        .unwrap_or_else(|error| panic!("{}: {}", file_name, error))
 }
 
-fn iterative_flash(mut octopuses: Octopuses) -> Octopuses {
-    for x in 0..octopuses.len() {
-        for y in 0..octopuses.len() {
-            recurent_flash((x, y), &mut octopuses)
+// Advances the octopuses by one step and returns the coordinates of the
+// octopuses which flashed, in the order the flashes happened.
+fn step(octopuses: &mut Octopuses) -> Vec<Coordinates> {
+    let mut flashed = Grid::new(octopuses.width(), octopuses.height(), false);
+    let mut to_flash = Vec::new();
+    for coordinates in octopuses.coordinates() {
+        octopuses[coordinates] += 1;
+        if octopuses[coordinates] >= TO_FLASH_THRESHOLD_VALUE {
+            flashed[coordinates] = true;
+            to_flash.push(coordinates);
         }
     }
-    octopuses
-}
-
-fn iterate(octopuses: Octopuses) -> (Octopuses, usize) {
-    let octopuses = increase_energy_level_by_one(octopuses);
-    let mut octopuses = iterative_flash(octopuses);
 
-    let mut flashed_counter = 0;
-    for x in 0..octopuses.len() {
-        for y in 0..octopuses.len() {
-            if octopuses[x][y] == ALREADY_FLASHED_VALUE {
-                flashed_counter += 1;
-                octopuses[x][y] = 0;
+    // Every flash charges the neighbours, which flash in turn once charged
+    // enough.
+    let flashes = flood_fill(
+        octopuses.size(),
+        to_flash,
+        Neighbourhood::Eight,
+        |adjacent| {
+            octopuses[adjacent] += 1;
+            let flashes = octopuses[adjacent] >= TO_FLASH_THRESHOLD_VALUE && !flashed[adjacent];
+            if flashes {
+                flashed[adjacent] = true;
             }
-        }
-    }
+            flashes
+        },
+    );
 
-    (octopuses, flashed_counter)
+    for coordinates in &flashes {
+        octopuses[*coordinates] = 0;
+    }
+    flashes
 }
 
 fn iterate_n_times(octopuses: Octopuses, times: usize) -> (Octopuses, usize) {
-    let mut octopuses = octopuses;
-    let mut flashes = 0;
-    for _ in 0..times {
-        let iteration_result = iterate(octopuses);
-        octopuses = iteration_result.0;
-        flashes += iteration_result.1;
-    }
-
-    (octopuses, flashes)
+    let mut simulator = Simulator::new(octopuses);
+    let flashes = simulator.total_flashes(times);
+    (simulator.state_at(times).clone(), flashes)
 }
 
 fn iteration_of_nth_simultaneousl_flash(octopuses: Octopuses, nth_flash: usize) -> usize {
-    let mut octopuses = octopuses;
-    let mut simultaneousl_flashes = 0;
-    let mut iteration = 0;
-    while simultaneousl_flashes != nth_flash {
-        iteration += 1;
-        let iteration_result = iterate(octopuses);
-        octopuses = iteration_result.0;
-        if iteration_result.1 == octopuses.len() * octopuses.len() {
-            simultaneousl_flashes += 1;
+    match nth_flash.checked_sub(1) {
+        Some(nth) => {
+            Simulator::new(octopuses)
+                .filter(Snapshot::all_flashed)
+                .nth(nth)
+                .unwrap()
+                .step
         }
+        None => 0,
     }
-
-    iteration
 }
 
 fn part_1_result(file_name: &str) {
@@ -136,48 +88,164 @@ fn part_2_result(file_name: &str) {
     );
 }
 
+#[derive(Default)]
+struct SimulationOptions {
+    trace: Option<usize>,
+    cycle: bool,
+    predict: Option<usize>,
+    visualiser: VisualiserOptions,
+    steps: usize,
+}
+
+impl SimulationOptions {
+    fn from_args<I: Iterator<Item = String>>(mut args: I) -> Self {
+        let mut options = SimulationOptions {
+            visualiser: VisualiserOptions {
+                scale: 8,
+                ..Default::default()
+            },
+            steps: 100,
+            ..Default::default()
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .unwrap_or_else(|| panic!("Missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "--trace" => options.trace = Some(value().parse().unwrap()),
+                "--cycle" => options.cycle = true,
+                "--predict" => options.predict = Some(value().parse().unwrap()),
+                "--steps" => options.steps = value().parse().unwrap(),
+                _ => {
+                    if !options.visualiser.parse_arg(&arg, &mut args) {
+                        panic!("Unknown argument {}", arg)
+                    }
+                }
+            }
+        }
+        options
+    }
+
+    fn any(&self) -> bool {
+        self.trace.is_some() || self.cycle || self.predict.is_some() || self.visualiser.any()
+    }
+}
+
+fn visualise(octopuses: Octopuses, options: &SimulationOptions) {
+    let mut visualiser =
+        Visualiser::from_options(&options.visualiser).unwrap_or_else(|error| panic!("{}", error));
+
+    let initial = Snapshot {
+        step: 0,
+        octopuses: octopuses.clone(),
+        cascade: Vec::new(),
+    };
+    let snapshots = Simulator::new(octopuses).take(options.steps);
+    for snapshot in std::iter::once(initial).chain(snapshots) {
+        let caption = format!("Step {}: {} flashes", snapshot.step, snapshot.flash_count());
+        visualiser.show(&snapshot, &caption).unwrap();
+    }
+
+    if let Some(summary) = visualiser.finish().unwrap() {
+        println!("{}", summary);
+    }
+}
+
+fn simulation_result(file_name: &str, options: &SimulationOptions) {
+    let data = load_data(file_name);
+
+    if let Some(steps) = options.trace {
+        let format_coordinates = |coordinates: &[Coordinates]| {
+            coordinates
+                .iter()
+                .map(|(x, y)| format!("{},{}", x, y))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        for snapshot in Simulator::new(data.clone()).take(steps) {
+            println!("Step {}: {} flashes", snapshot.step, snapshot.flash_count());
+            if !snapshot.cascade.is_empty() {
+                println!("  flashed: {}", format_coordinates(&snapshot.flashed()));
+                println!("  cascade: {}", format_coordinates(&snapshot.cascade));
+            }
+        }
+    }
+
+    if options.visualiser.any() {
+        visualise(data.clone(), options);
+    }
+
+    let mut simulator = Simulator::new(data);
+    if options.cycle {
+        let cycle = simulator.find_cycle();
+        println!(
+            "State after step {} repeats every {} steps",
+            cycle.start, cycle.length
+        );
+    }
+    if let Some(step) = options.predict {
+        println!("After step {}:", step);
+        println!("{}", simulator.state_at(step));
+        println!(
+            "Flashes during step {}: {}",
+            step,
+            simulator.flashes_at(step)
+        );
+        println!("Total flashes: {}", simulator.total_flashes(step));
+    }
+}
+
 fn main() {
     const DATA_FILENAME: &str = "./resources/data.txt";
+    let simulation_options = SimulationOptions::from_args(env::args().skip(1));
+    if simulation_options.any() {
+        simulation_result(DATA_FILENAME, &simulation_options);
+        return;
+    }
     part_1_result(DATA_FILENAME);
     part_2_result(DATA_FILENAME);
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{iterate, iterate_n_times, iteration_of_nth_simultaneousl_flash, load_data};
+    use crate::{iterate_n_times, iteration_of_nth_simultaneousl_flash, load_data, step};
+    use common::grid::Grid;
 
     #[test]
     fn part_1_test_data_1() {
         const TEST_DATA_FILENAME: &str = "./resources/test_data_1.txt";
-        let data = load_data(TEST_DATA_FILENAME);
+        let mut data = load_data(TEST_DATA_FILENAME);
 
-        let iteration_result = iterate(data.clone());
+        let flashes = step(&mut data).len();
 
         assert_eq!(
-            iteration_result,
+            (data.clone(), flashes),
             (
-                vec![
+                Grid::from_rows(vec![
                     vec![3, 4, 5, 4, 3],
                     vec![4, 0, 0, 0, 4],
                     vec![5, 0, 0, 0, 5],
                     vec![4, 0, 0, 0, 4],
                     vec![3, 4, 5, 4, 3],
-                ],
+                ])
+                .unwrap(),
                 9
             )
         );
 
-        let iteration_result = iterate(iteration_result.0);
+        let flashes = step(&mut data).len();
         assert_eq!(
-            iteration_result,
+            (data, flashes),
             (
-                vec![
+                Grid::from_rows(vec![
                     vec![4, 5, 6, 5, 4],
                     vec![5, 1, 1, 1, 5],
                     vec![6, 1, 1, 1, 6],
                     vec![5, 1, 1, 1, 5],
                     vec![4, 5, 6, 5, 4],
-                ],
+                ])
+                .unwrap(),
                 0
             )
         );
@@ -193,7 +261,7 @@ mod tests {
         assert_eq!(
             (data, flashes),
             (
-                vec![
+                Grid::from_rows(vec![
                     vec![0, 3, 9, 7, 6, 6, 6, 8, 6, 6],
                     vec![0, 7, 4, 9, 7, 6, 6, 9, 1, 8],
                     vec![0, 0, 5, 3, 9, 7, 6, 9, 3, 3],
@@ -204,7 +272,8 @@ mod tests {
                     vec![9, 3, 2, 2, 2, 2, 8, 9, 6, 6],
                     vec![7, 9, 2, 2, 2, 8, 6, 8, 6, 6],
                     vec![6, 7, 8, 9, 9, 9, 8, 7, 6, 6],
-                ],
+                ])
+                .unwrap(),
                 1656
             )
         );
@@ -219,4 +288,37 @@ mod tests {
 
         assert_eq!(iterations, 195);
     }
+
+    #[test]
+    fn non_square_grid() {
+        let mut data = Grid::parse_digits("99999\n91119\n99999")
+            .unwrap()
+            .map(|octopus| *octopus as u32);
+
+        let flashes = step(&mut data).len();
+
+        assert_eq!(
+            (data, flashes),
+            (
+                Grid::from_rows(vec![
+                    vec![0, 0, 0, 0, 0],
+                    vec![0, 9, 8, 9, 0],
+                    vec![0, 0, 0, 0, 0],
+                ])
+                .unwrap(),
+                12
+            )
+        );
+    }
+
+    #[test]
+    fn large_grid_cascade() {
+        let mut data = Grid::new(400, 300, 8);
+        data[(150, 200)] = 9;
+
+        let flashes = step(&mut data).len();
+
+        assert_eq!(flashes, 400 * 300);
+        assert!(data.iter().all(|octopus| *octopus == 0));
+    }
 }
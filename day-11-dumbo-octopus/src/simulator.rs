@@ -0,0 +1,340 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use common::{grid::Coordinates, visualisation::Render};
+
+use crate::{step, Octopuses};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Snapshot {
+    pub step: usize,
+    pub octopuses: Octopuses,
+    // Octopuses in the order they flashed: first those charged by the step
+    // itself, then those charged by their flashing neighbours.
+    pub cascade: Vec<Coordinates>,
+}
+
+impl Snapshot {
+    pub fn flash_count(&self) -> usize {
+        self.cascade.len()
+    }
+
+    pub fn flashed(&self) -> Vec<Coordinates> {
+        let mut flashed = self.cascade.clone();
+        flashed.sort_unstable();
+        flashed
+    }
+
+    pub fn all_flashed(&self) -> bool {
+        self.flash_count() == self.octopuses.len()
+    }
+}
+
+// Octopuses which just flashed are drawn as a bright `*`, the others as their
+// energy level, brighter the closer they are to flashing.
+impl Render for Snapshot {
+    fn size(&self) -> (usize, usize) {
+        self.octopuses.size()
+    }
+
+    fn glyph(&self, coordinates: Coordinates) -> char {
+        match self.octopuses[coordinates] {
+            0 => '*',
+            energy => char::from_digit(energy.min(9), 10).unwrap(),
+        }
+    }
+
+    fn colour(&self, coordinates: Coordinates) -> [u8; 3] {
+        match self.octopuses[coordinates].min(9) as u8 {
+            0 => [255, 230, 90],
+            energy => [10 + energy * 8, 30 + energy * 15, 70 + energy * 18],
+        }
+    }
+}
+
+// The state after step `start + length` is the same as after step `start`, so
+// from `start` on everything repeats every `length` steps.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    // The already simulated step which behaves the same as `step`.
+    fn equivalent_step(&self, step: usize) -> usize {
+        if step <= self.start {
+            step
+        } else {
+            self.start + (step - self.start - 1) % self.length + 1
+        }
+    }
+}
+
+// Steps the octopuses and remembers every state until one repeats. After that
+// later states are worked out from the remembered ones, so nothing more is
+// stored. Iterating yields a snapshot of every step, whatever was queried
+// before.
+pub struct Simulator {
+    // State after every simulated step up to the end of the first cycle,
+    // `states[0]` is the initial one.
+    states: Vec<Octopuses>,
+    flash_counts: Vec<usize>,
+    // Steps of the stored states, by the hash of the state.
+    seen: HashMap<u64, Vec<usize>>,
+    cycle: Option<Cycle>,
+    // Steps the iterator went through, independent of the simulated ones.
+    step: usize,
+}
+
+fn state_hash(octopuses: &Octopuses) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    octopuses.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Simulator {
+    pub fn new(octopuses: Octopuses) -> Self {
+        let seen = HashMap::from([(state_hash(&octopuses), vec![0])]);
+        Self {
+            states: vec![octopuses],
+            flash_counts: vec![0],
+            seen,
+            cycle: None,
+            step: 0,
+        }
+    }
+
+    pub fn steps_simulated(&self) -> usize {
+        self.states.len() - 1
+    }
+
+    // Simulates until a state repeats. The number of states is finite, but
+    // for large grids it may take a long time before that happens.
+    pub fn find_cycle(&mut self) -> Cycle {
+        loop {
+            if let Some(cycle) = self.cycle {
+                return cycle;
+            }
+            self.advance();
+        }
+    }
+
+    pub fn state_at(&mut self, step: usize) -> &Octopuses {
+        let step = self.simulate_to(step);
+        &self.states[step]
+    }
+
+    // Number of flashes during `step`.
+    pub fn flashes_at(&mut self, step: usize) -> usize {
+        let step = self.simulate_to(step);
+        self.flash_counts[step]
+    }
+
+    // Number of flashes during the first `steps` steps.
+    pub fn total_flashes(&mut self, steps: usize) -> usize {
+        let simulated = self.simulate_to(steps);
+        let cycle = match self.cycle {
+            Some(cycle) if steps > self.steps_simulated() => cycle,
+            _ => return self.flash_counts[..=simulated].iter().sum(),
+        };
+
+        let before_cycle = self.flash_counts[..=cycle.start].iter().sum::<usize>();
+        let cycle_flashes = &self.flash_counts[cycle.start + 1..=cycle.start + cycle.length];
+        let cycles = (steps - cycle.start) / cycle.length;
+        let remaining = (steps - cycle.start) % cycle.length;
+        before_cycle
+            + cycles * cycle_flashes.iter().sum::<usize>()
+            + cycle_flashes[..remaining].iter().sum::<usize>()
+    }
+
+    // Simulates up to `step` unless a cycle allows skipping ahead. Returns the
+    // simulated step equivalent to `step`.
+    fn simulate_to(&mut self, step: usize) -> usize {
+        while self.steps_simulated() < step {
+            if let Some(cycle) = self.cycle {
+                return cycle.equivalent_step(step);
+            }
+            self.advance();
+        }
+        step
+    }
+
+    // Simulates and stores the step after the last stored one.
+    fn advance(&mut self) {
+        let mut octopuses = self.states[self.steps_simulated()].clone();
+        let flashes = step(&mut octopuses).len();
+        self.record(&octopuses, flashes);
+    }
+
+    // Stores the state after the next step, noticing if it was seen before.
+    fn record(&mut self, octopuses: &Octopuses, flashes: usize) {
+        let step = self.states.len();
+        let states = &self.states;
+        let steps = self.seen.entry(state_hash(octopuses)).or_default();
+        if let Some(start) = steps
+            .iter()
+            .find(|seen_step| states[**seen_step] == *octopuses)
+        {
+            self.cycle = Some(Cycle {
+                start: *start,
+                length: step - start,
+            });
+        }
+        steps.push(step);
+        self.states.push(octopuses.clone());
+        self.flash_counts.push(flashes);
+    }
+}
+
+impl Iterator for Simulator {
+    type Item = Snapshot;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let previous = match self.cycle {
+            Some(cycle) => cycle.equivalent_step(self.step),
+            None => self.step,
+        };
+        let mut octopuses = self.states[previous].clone();
+        let cascade = step(&mut octopuses);
+        self.step += 1;
+
+        if self.cycle.is_none() && self.step > self.steps_simulated() {
+            self.record(&octopuses, cascade.len());
+        }
+
+        Some(Snapshot {
+            step: self.step,
+            octopuses,
+            cascade,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_data;
+
+    #[test]
+    fn test_snapshots() {
+        let data = load_data("./resources/test_data_1.txt");
+
+        let snapshots = Simulator::new(data).take(2).collect::<Vec<_>>();
+
+        assert_eq!(snapshots[0].step, 1);
+        assert_eq!(snapshots[0].flash_count(), 9);
+        assert_eq!(&snapshots[0].cascade[..3], &[(1, 1), (1, 2), (1, 3)]);
+        assert_eq!(snapshots[0].cascade[8], (2, 2));
+        assert_eq!(
+            snapshots[0].flashed(),
+            vec![
+                (1, 1),
+                (1, 2),
+                (1, 3),
+                (2, 1),
+                (2, 2),
+                (2, 3),
+                (3, 1),
+                (3, 2),
+                (3, 3)
+            ]
+        );
+        assert_eq!(snapshots[1].step, 2);
+        assert!(snapshots[1].cascade.is_empty());
+    }
+
+    #[test]
+    fn test_all_flashed() {
+        let data = load_data("./resources/test_data_2.txt");
+
+        let first_synchronised = Simulator::new(data).find(Snapshot::all_flashed).unwrap();
+
+        assert_eq!(first_synchronised.step, 195);
+    }
+
+    #[test]
+    fn test_cycle_predictions() {
+        let data = load_data("./resources/test_data_2.txt");
+        let mut simulator = Simulator::new(data.clone());
+
+        let cycle = simulator.find_cycle();
+
+        assert_eq!(cycle.length, 10);
+        assert!(cycle.start >= 195);
+        assert_eq!(simulator.total_flashes(100), 1656);
+        let far_step = 1_000_000_007;
+        let equivalent = cycle.start + (far_step - cycle.start) % cycle.length;
+        let expected = simulator.state_at(equivalent).clone();
+        assert_eq!(simulator.state_at(far_step), &expected);
+        assert!(simulator.steps_simulated() < 1000);
+
+        let mut reference = Simulator::new(data);
+        let steps = cycle.start + 3 * cycle.length + 7;
+        let expected_flashes = reference
+            .by_ref()
+            .take(steps)
+            .map(|snapshot| snapshot.flash_count())
+            .sum::<usize>();
+        assert_eq!(simulator.total_flashes(steps), expected_flashes);
+        assert_eq!(simulator.flashes_at(steps), reference.flashes_at(steps));
+    }
+
+    #[test]
+    fn test_no_states_stored_after_cycle() {
+        let data = load_data("./resources/test_data_2.txt");
+        let mut simulator = Simulator::new(data.clone());
+        let cycle = simulator.find_cycle();
+        let stored = simulator.steps_simulated();
+
+        let snapshots = simulator
+            .by_ref()
+            .take(stored + 25)
+            .skip(stored)
+            .collect::<Vec<_>>();
+
+        assert_eq!(stored, cycle.start + cycle.length);
+        assert_eq!(simulator.steps_simulated(), stored);
+        let mut octopuses = data;
+        for _ in 0..stored {
+            step(&mut octopuses);
+        }
+        for (offset, snapshot) in snapshots.into_iter().enumerate() {
+            let cascade = step(&mut octopuses);
+            assert_eq!(snapshot.step, stored + offset + 1);
+            assert_eq!(snapshot.octopuses, octopuses);
+            assert_eq!(snapshot.cascade, cascade);
+        }
+    }
+
+    #[test]
+    fn test_queries_dont_move_iterator() {
+        let data = load_data("./resources/test_data_2.txt");
+        let mut simulator = Simulator::new(data.clone());
+        let expected = Simulator::new(data).take(300).collect::<Vec<_>>();
+
+        simulator.state_at(100);
+        let first = simulator.by_ref().take(3).collect::<Vec<_>>();
+        assert_eq!(simulator.total_flashes(10), 204);
+        simulator.find_cycle();
+        let more = simulator.by_ref().take(297).collect::<Vec<_>>();
+        assert_eq!(simulator.flashes_at(2), expected[1].flash_count());
+
+        assert_eq!(&first[..], &expected[..3]);
+        assert_eq!(&more[..], &expected[3..]);
+    }
+
+    #[test]
+    fn test_render_snapshot() {
+        let data = load_data("./resources/test_data_1.txt");
+
+        let snapshot = Simulator::new(data).next().unwrap();
+
+        assert_eq!(Render::size(&snapshot), (5, 5));
+        assert_eq!(snapshot.glyph((0, 0)), '3');
+        assert_eq!(snapshot.glyph((2, 2)), '*');
+        assert_eq!(snapshot.colour((2, 2)), [255, 230, 90]);
+    }
+}
@@ -0,0 +1,52 @@
+use common::graph::Graph;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CaveSize {
+    Small,
+    Big,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Cave {
+    pub size: CaveSize,
+    pub visit_limit: Option<usize>,
+}
+
+impl Cave {
+    pub fn small() -> Self {
+        Self {
+            size: CaveSize::Small,
+            visit_limit: Some(1),
+        }
+    }
+
+    pub fn big() -> Self {
+        Self {
+            size: CaveSize::Big,
+            visit_limit: None,
+        }
+    }
+
+    // The puzzle input marks small caves with lowercase names.
+    pub fn from_name(name: &str) -> Self {
+        if name.to_lowercase() == name {
+            Self::small()
+        } else {
+            Self::big()
+        }
+    }
+}
+
+pub type CaveSystem = Graph<Cave>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cave_from_name() {
+        assert_eq!(Cave::from_name("start"), Cave::small());
+        assert_eq!(Cave::from_name("dc"), Cave::small());
+        assert_eq!(Cave::from_name("HN"), Cave::big());
+    }
+}
@@ -0,0 +1,96 @@
+use std::io::{self, Write};
+
+use common::graph::NodeId;
+
+use crate::cave::{CaveSize, CaveSystem};
+
+const PATH_COLOURS: [&str; 8] = [
+    "#e41a1c", "#377eb8", "#4daf4a", "#984ea3", "#ff7f00", "#a65628", "#f781bf", "#999999",
+];
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Writes the cave system as an undirected Graphviz graph. Every path gets its
+// own colour and is drawn as a chain of directed edges on top of the tunnels.
+pub fn write_dot<W: Write>(
+    cave_system: &CaveSystem,
+    paths: &[Vec<NodeId>],
+    writer: &mut W,
+) -> io::Result<()> {
+    writeln!(writer, "graph caves {{")?;
+    writeln!(writer, "  node [style=filled];")?;
+    for cave in cave_system.nodes() {
+        let style = match cave_system.attributes(cave).size {
+            CaveSize::Small => "shape=ellipse, fillcolor=\"#cce5ff\"",
+            CaveSize::Big => "shape=box, fillcolor=\"#ffd8a8\", penwidth=2",
+        };
+        writeln!(writer, "  {} [{}];", quote(cave_system.name(cave)), style)?;
+    }
+
+    for (a, b) in cave_system.edges().filter(|(a, b)| a < b) {
+        writeln!(
+            writer,
+            "  {} -- {} [color=\"#bbbbbb\"];",
+            quote(cave_system.name(a)),
+            quote(cave_system.name(b))
+        )?;
+    }
+
+    for (index, path) in paths.iter().enumerate() {
+        let colour = PATH_COLOURS[index % PATH_COLOURS.len()];
+        for step in path.windows(2) {
+            writeln!(
+                writer,
+                "  {} -- {} [color=\"{}\", penwidth=2, dir=forward, label=\"{}\", fontcolor=\"{}\"];",
+                quote(cave_system.name(step[0])),
+                quote(cave_system.name(step[1])),
+                colour,
+                index + 1,
+                colour
+            )?;
+        }
+    }
+
+    writeln!(writer, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cave::Cave;
+
+    #[test]
+    fn test_write_dot() {
+        let mut cave_system = CaveSystem::new();
+        let start = cave_system.add_node("start", Cave::small());
+        let a = cave_system.add_node("A", Cave::big());
+        let end = cave_system.add_node("end", Cave::small());
+        cave_system.add_undirected_edge(start, a);
+        cave_system.add_undirected_edge(a, end);
+
+        let mut output = Vec::new();
+        write_dot(&cave_system, &[vec![start, a, end]], &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            r##"graph caves {
+  node [style=filled];
+  "start" [shape=ellipse, fillcolor="#cce5ff"];
+  "A" [shape=box, fillcolor="#ffd8a8", penwidth=2];
+  "end" [shape=ellipse, fillcolor="#cce5ff"];
+  "start" -- "A" [color="#bbbbbb"];
+  "A" -- "end" [color="#bbbbbb"];
+  "start" -- "A" [color="#e41a1c", penwidth=2, dir=forward, label="1", fontcolor="#e41a1c"];
+  "A" -- "end" [color="#e41a1c", penwidth=2, dir=forward, label="1", fontcolor="#e41a1c"];
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote(r#"a"b\c"#), r#""a\"b\\c""#);
+    }
+}
@@ -1,158 +1,185 @@
+mod cave;
+mod dot;
+mod path_counting;
+mod paths_iterator;
+mod visit_policy;
+
+use cave::{Cave, CaveSystem};
+use common::graph::NodeId;
+use dot::write_dot;
+use path_counting::count_paths;
+use paths_iterator::{PathOrder, Paths};
 use std::{
-    collections::{HashMap, HashSet},
+    env,
     fs::File,
-    io::{BufRead, BufReader},
-    iter::FromIterator,
+    io::{BufRead, BufReader, BufWriter, Write},
 };
+use visit_policy::{PolicyError, VisitLimits, VisitPolicy};
+
+const START_CAVE: &str = "start";
+const END_CAVE: &str = "end";
 
-fn load_data(file_name: &str) -> HashMap<String, HashSet<String>> {
-    let file = File::open(file_name).expect(&format!("Can't read file {}", file_name));
+fn load_data(file_name: &str) -> CaveSystem {
+    let file = File::open(file_name).unwrap_or_else(|_| panic!("Can't read file {}", file_name));
     let file = BufReader::new(file);
 
-    let mut result: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut result = CaveSystem::new();
 
     for line in file.lines() {
         let line = line.unwrap();
         let points = line.split('-').collect::<Vec<_>>();
-        if let Some(val) = result.get_mut(points[0]) {
-            val.insert(points[1].to_string());
-        } else {
-            result.insert(
-                points[0].to_string(),
-                HashSet::from_iter([points[1].to_string()]),
-            );
-        }
-
-        if let Some(val) = result.get_mut(points[1]) {
-            val.insert(points[0].to_string());
-        } else {
-            result.insert(
-                points[1].to_string(),
-                HashSet::from_iter([points[0].to_string()]),
-            );
-        }
+        let a = result.add_node(points[0], Cave::from_name(points[0]));
+        let b = result.add_node(points[1], Cave::from_name(points[1]));
+        result.add_undirected_edge(a, b);
     }
 
     result
 }
 
-fn find_paths(
-    data: HashMap<String, HashSet<String>>,
-    max_visit_small_caves: usize,
-) -> Vec<Vec<String>> {
-    fn rec_find_path(
-        stop: String,
-        data: HashMap<String, HashSet<String>>,
-        current_paths: Vec<Vec<String>>,
-        visited_small_caves: usize,
-    ) -> Vec<Vec<String>> {
-        let mut paths = HashSet::new();
+// Paths from `source` to `sink` allowed by the policy, as cave names.
+fn find_paths<'a, Policy: VisitPolicy>(
+    cave_system: &'a CaveSystem,
+    source: NodeId,
+    sink: NodeId,
+    policy: &'a Policy,
+    order: PathOrder,
+) -> Result<impl Iterator<Item = Vec<String>> + 'a, PolicyError> {
+    Ok(Paths::new(cave_system, source, sink, policy, order)?
+        .map(move |path| cave_system.names(&path)))
+}
 
-        for path in current_paths {
-            let last_point = path.last().unwrap();
-            if *last_point == stop {
-                paths.insert(path);
-            } else {
-                let mut data_copy = data.clone();
-                let mut copy_visited_small_caves = visited_small_caves;
-                if last_point == "start" {
-                    data_copy.remove(last_point);
-                    if let Some(points) = data.get(last_point) {
-                        for point in points {
-                            let mut new_path = path.clone();
-                            new_path.push(point.clone());
-                            paths.extend(rec_find_path(
-                                stop.clone(),
-                                data_copy.clone(),
-                                vec![new_path],
-                                copy_visited_small_caves,
-                            ));
-                        }
-                    }
-                } else if last_point.to_lowercase() == *last_point {
-                    if copy_visited_small_caves <= 1 {
-                        data_copy.remove(last_point);
-                        if let Some(points) = data.get(last_point) {
-                            for point in points {
-                                let mut new_path = path.clone();
-                                new_path.push(point.clone());
-                                paths.extend(rec_find_path(
-                                    stop.clone(),
-                                    data_copy.clone(),
-                                    vec![new_path],
-                                    copy_visited_small_caves,
-                                ));
-                            }
-                        }
-                    } else {
-                        copy_visited_small_caves -= 1;
-                        data_copy.remove(last_point);
-                        if let Some(points) = data.get(last_point) {
-                            for point in points {
-                                let mut new_path = path.clone();
-                                new_path.push(point.clone());
-                                paths.extend(rec_find_path(
-                                    stop.clone(),
-                                    data.clone(),
-                                    vec![new_path.clone()],
-                                    copy_visited_small_caves,
-                                ));
-                                paths.extend(rec_find_path(
-                                    stop.clone(),
-                                    data_copy.clone(),
-                                    vec![new_path],
-                                    visited_small_caves,
-                                ));
-                            }
-                        }
-                    }
-                } else if let Some(points) = data.get(last_point) {
-                    for point in points {
-                        let mut new_path = path.clone();
-                        new_path.push(point.clone());
-                        paths.extend(rec_find_path(
-                            stop.clone(),
-                            data_copy.clone(),
-                            vec![new_path],
-                            copy_visited_small_caves,
-                        ));
-                    }
-                }
-            }
-        }
+fn start_and_end(cave_system: &CaveSystem) -> (NodeId, NodeId) {
+    (
+        cave_system.node_id(START_CAVE).unwrap(),
+        cave_system.node_id(END_CAVE).unwrap(),
+    )
+}
 
-        paths.into_iter().collect()
+fn part_policy(cave_system: &CaveSystem, part: usize) -> VisitLimits {
+    match part {
+        1 => VisitLimits::small_caves_once(cave_system),
+        2 => VisitLimits::single_small_cave_twice(cave_system),
+        _ => panic!("There is no part {}", part),
     }
-
-    rec_find_path(
-        "end".to_string(),
-        data,
-        vec![vec!["start".to_string()]],
-        max_visit_small_caves,
-    )
 }
 
 fn part_1_result(file_name: &str) {
     let data = load_data(file_name);
-    let paths = find_paths(data, 1);
-    println!("Part 1. Result: {}", paths.len());
+    let (start, end) = start_and_end(&data);
+    let policy = part_policy(&data, 1);
+    println!(
+        "Part 1. Result: {}",
+        count_paths(&data, start, end, &policy).unwrap()
+    );
 }
 
 fn part_2_result(file_name: &str) {
     let data = load_data(file_name);
-    let paths = find_paths(data, 2);
-    println!("Part 2. Result: {}", paths.len());
+    let (start, end) = start_and_end(&data);
+    let policy = part_policy(&data, 2);
+    println!(
+        "Part 2. Result: {}",
+        count_paths(&data, start, end, &policy).unwrap()
+    );
+}
+
+const USAGE: &str = "Usage: [--list] [--order <lexicographic|shortest>] [--take <n>] \
+[--dot <file> [--highlight <n>]] [--part <1|2>] [--repeat <extra visits>] [--limit <cave>=<visits|inf>] \
+[--forbid <cave>] [--through <cave>]";
+
+fn cave_from_arg(cave_system: &CaveSystem, name: &str) -> NodeId {
+    cave_system
+        .node_id(name)
+        .unwrap_or_else(|| panic!("There is no cave {}", name))
+}
+
+fn custom_result(file_name: &str, args: &[String]) {
+    let data = load_data(file_name);
+    let (start, end) = start_and_end(&data);
+    // The part picks the base policy, so it is applied before the options
+    // modifying it, wherever it is given.
+    let part = args
+        .iter()
+        .position(|arg| arg == "--part")
+        .map(|index| args.get(index + 1).expect(USAGE).parse().expect(USAGE))
+        .unwrap_or(1);
+    let mut policy = part_policy(&data, part);
+    let mut list = false;
+    let mut order = PathOrder::Lexicographic;
+    let mut take = usize::MAX;
+    let mut dot = None;
+    let mut highlight = 0;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().map(String::as_str).expect(USAGE);
+        match arg.as_str() {
+            "--list" => list = true,
+            "--order" => {
+                order = match value() {
+                    "lexicographic" => PathOrder::Lexicographic,
+                    "shortest" => PathOrder::ShortestFirst,
+                    _ => panic!("{}", USAGE),
+                }
+            }
+            "--take" => take = value().parse().expect(USAGE),
+            "--dot" => dot = Some(value().to_string()),
+            "--highlight" => highlight = value().parse().expect(USAGE),
+            "--part" => {
+                value();
+            }
+            "--repeat" => policy = policy.with_single_cave_repeat(value().parse().expect(USAGE)),
+            "--limit" => {
+                let (cave, visits) = value().split_once('=').expect(USAGE);
+                let limit = match visits {
+                    "inf" => None,
+                    visits => Some(visits.parse().expect(USAGE)),
+                };
+                policy = policy.with_visit_limit(cave_from_arg(&data, cave), limit);
+            }
+            "--forbid" => policy = policy.with_forbidden(cave_from_arg(&data, value())),
+            "--through" => policy = policy.with_waypoint(cave_from_arg(&data, value())),
+            _ => panic!("{}", USAGE),
+        }
+    }
+
+    policy
+        .validate(&data, start, end)
+        .unwrap_or_else(|error| panic!("Invalid visit policy: {}", error));
+    if let Some(dot) = dot {
+        let paths = Paths::new(&data, start, end, &policy, order)
+            .unwrap()
+            .take(highlight)
+            .collect::<Vec<_>>();
+        let file = File::create(&dot).unwrap_or_else(|_| panic!("Can't create file {}", dot));
+        let mut file = BufWriter::new(file);
+        write_dot(&data, &paths, &mut file).unwrap();
+        file.flush().unwrap();
+    } else if list {
+        let paths = find_paths(&data, start, end, &policy, order).unwrap();
+        for path in paths.take(take) {
+            println!("{}", path.join(","));
+        }
+    } else {
+        let count = count_paths(&data, start, end, &policy).unwrap();
+        println!("Result: {}", count);
+    }
 }
 
 fn main() {
     const DATA_FILENAME: &str = "./resources/data.txt";
-    part_1_result(DATA_FILENAME);
-    part_2_result(DATA_FILENAME);
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.is_empty() {
+        part_1_result(DATA_FILENAME);
+        part_2_result(DATA_FILENAME);
+    } else {
+        custom_result(DATA_FILENAME, &args);
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{find_paths, load_data};
+    use crate::{find_paths, load_data, part_policy, start_and_end, PathOrder};
 
     #[test]
     fn part_1_a_test_data() {
@@ -223,7 +250,11 @@ mod tests {
             vec!["start".to_string(), "b".to_string(), "end".to_string()],
         ];
         expected.sort();
-        let mut actual = find_paths(data, 1);
+        let (start, end) = start_and_end(&data);
+        let policy = part_policy(&data, 1);
+        let mut actual = find_paths(&data, start, end, &policy, PathOrder::Lexicographic)
+            .unwrap()
+            .collect::<Vec<_>>();
 
         actual.sort();
         assert_eq!(actual, expected);
@@ -362,7 +393,11 @@ mod tests {
             ],
         ];
         expected.sort();
-        let mut actual = find_paths(data, 1);
+        let (start, end) = start_and_end(&data);
+        let policy = part_policy(&data, 1);
+        let mut actual = find_paths(&data, start, end, &policy, PathOrder::Lexicographic)
+            .unwrap()
+            .collect::<Vec<_>>();
 
         actual.sort();
         assert_eq!(actual, expected);
@@ -688,7 +723,11 @@ fn part_2_a_test_data() {
         vec!["start".to_string(), "b".to_string(), "end".to_string()],
     ];
     expected.sort();
-    let mut actual = find_paths(data, 2);
+    let (start, end) = start_and_end(&data);
+    let policy = part_policy(&data, 2);
+    let mut actual = find_paths(&data, start, end, &policy, PathOrder::Lexicographic)
+        .unwrap()
+        .collect::<Vec<_>>();
     actual.sort();
 
     assert_eq!(actual.len(), expected.len());
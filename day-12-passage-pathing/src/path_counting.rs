@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use common::graph::NodeId;
+
+use crate::{
+    cave::CaveSystem,
+    visit_policy::{PolicyError, VisitPolicy},
+};
+
+pub fn count_paths<Policy: VisitPolicy>(
+    cave_system: &CaveSystem,
+    source: NodeId,
+    sink: NodeId,
+    policy: &Policy,
+) -> Result<usize, PolicyError> {
+    policy.validate(cave_system, source, sink)?;
+
+    fn rec_count_paths<Policy: VisitPolicy>(
+        cave_system: &CaveSystem,
+        policy: &Policy,
+        (source, sink): (NodeId, NodeId),
+        (cave, state): (NodeId, Policy::State),
+        memo: &mut HashMap<(NodeId, Policy::State), usize>,
+    ) -> usize {
+        if cave == sink {
+            return policy.accepts(&state) as usize;
+        }
+        if let Some(count) = memo.get(&(cave, state.clone())) {
+            return *count;
+        }
+
+        let mut count = 0;
+        for &next in cave_system.neighbours(cave) {
+            if next == source {
+                continue;
+            }
+            if let Some(next_state) = policy.enter(&state, next) {
+                count += rec_count_paths(
+                    cave_system,
+                    policy,
+                    (source, sink),
+                    (next, next_state),
+                    memo,
+                );
+            }
+        }
+
+        memo.insert((cave, state), count);
+        count
+    }
+
+    Ok(rec_count_paths(
+        cave_system,
+        policy,
+        (source, sink),
+        (source, policy.initial_state(source)),
+        &mut HashMap::new(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        find_paths, load_data, paths_iterator::PathOrder, start_and_end, visit_policy::VisitLimits,
+    };
+
+    #[test]
+    fn test_count_paths_matches_find_paths() {
+        for file_name in [
+            "./resources/test_data.txt",
+            "./resources/test_data_2.txt",
+            "./resources/test_data_3.txt",
+        ] {
+            let data = load_data(file_name);
+            let (start, end) = start_and_end(&data);
+            for extra in 0..=2 {
+                let policy = VisitLimits::small_caves_once(&data).with_single_cave_repeat(extra);
+                assert_eq!(
+                    count_paths(&data, start, end, &policy),
+                    Ok(
+                        find_paths(&data, start, end, &policy, PathOrder::Lexicographic)
+                            .unwrap()
+                            .count()
+                    )
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_paths() {
+        let expected = [
+            ("./resources/test_data.txt", 10, 36),
+            ("./resources/test_data_2.txt", 19, 103),
+            ("./resources/test_data_3.txt", 226, 3509),
+        ];
+        for (file_name, part_1, part_2) in expected {
+            let data = load_data(file_name);
+            let (start, end) = start_and_end(&data);
+            let part_1_policy = VisitLimits::small_caves_once(&data);
+            let part_2_policy = VisitLimits::single_small_cave_twice(&data);
+            assert_eq!(count_paths(&data, start, end, &part_1_policy), Ok(part_1));
+            assert_eq!(count_paths(&data, start, end, &part_2_policy), Ok(part_2));
+        }
+    }
+}
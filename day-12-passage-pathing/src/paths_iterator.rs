@@ -0,0 +1,252 @@
+use common::graph::NodeId;
+
+use crate::{
+    cave::CaveSystem,
+    visit_policy::{PolicyError, VisitPolicy},
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PathOrder {
+    // Paths compared cave by cave using cave names.
+    Lexicographic,
+    // Fewest caves first, equally long paths in lexicographic order.
+    ShortestFirst,
+}
+
+struct Frame<State> {
+    state: State,
+    next_neighbour: usize,
+}
+
+// Walks the cave system depth first with an explicit stack, so only the current
+// path is kept in memory. Shortest-first order is produced by iterative
+// deepening: every round looks for paths with exactly one more cave.
+pub struct Paths<'a, Policy: VisitPolicy> {
+    policy: &'a Policy,
+    source: NodeId,
+    sink: NodeId,
+    order: PathOrder,
+    neighbours: Vec<Vec<NodeId>>,
+    path: Vec<NodeId>,
+    stack: Vec<Frame<Policy::State>>,
+    path_length: usize,
+    cut_at_path_length: bool,
+    started: bool,
+}
+
+impl<'a, Policy: VisitPolicy> Paths<'a, Policy> {
+    pub fn new(
+        cave_system: &'a CaveSystem,
+        source: NodeId,
+        sink: NodeId,
+        policy: &'a Policy,
+        order: PathOrder,
+    ) -> Result<Self, PolicyError> {
+        policy.validate(cave_system, source, sink)?;
+        let neighbours = cave_system
+            .nodes()
+            .map(|cave| {
+                let mut neighbours = cave_system.neighbours(cave).to_vec();
+                neighbours.sort_by_key(|neighbour| cave_system.name(*neighbour));
+                neighbours
+            })
+            .collect();
+
+        Ok(Self {
+            policy,
+            source,
+            sink,
+            order,
+            neighbours,
+            path: Vec::new(),
+            stack: Vec::new(),
+            path_length: 1,
+            cut_at_path_length: false,
+            started: false,
+        })
+    }
+
+    fn restart(&mut self) -> bool {
+        let more_rounds = match self.order {
+            PathOrder::Lexicographic => !self.started,
+            PathOrder::ShortestFirst => !self.started || self.cut_at_path_length,
+        };
+        if !more_rounds || self.source == self.sink {
+            return false;
+        }
+
+        self.started = true;
+        self.path_length += 1;
+        self.cut_at_path_length = false;
+        self.path = vec![self.source];
+        self.stack = vec![Frame {
+            state: self.policy.initial_state(self.source),
+            next_neighbour: 0,
+        }];
+        true
+    }
+
+    fn accepts_length(&self, length: usize) -> bool {
+        self.order == PathOrder::Lexicographic || length == self.path_length
+    }
+
+    fn can_extend_to(&mut self, length: usize) -> bool {
+        if self.order == PathOrder::ShortestFirst && length >= self.path_length {
+            self.cut_at_path_length = true;
+            false
+        } else {
+            true
+        }
+    }
+}
+
+impl<'a, Policy: VisitPolicy> Iterator for Paths<'a, Policy> {
+    type Item = Vec<NodeId>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started && self.source == self.sink {
+            self.started = true;
+            let state = self.policy.initial_state(self.source);
+            return self.policy.accepts(&state).then(|| vec![self.source]);
+        }
+
+        loop {
+            let frame = match self.stack.last_mut() {
+                Some(frame) => frame,
+                None => {
+                    if self.restart() {
+                        continue;
+                    }
+                    return None;
+                }
+            };
+
+            let cave = *self.path.last().unwrap();
+            let neighbour = match self.neighbours[cave].get(frame.next_neighbour) {
+                Some(neighbour) => *neighbour,
+                None => {
+                    self.stack.pop();
+                    self.path.pop();
+                    continue;
+                }
+            };
+            frame.next_neighbour += 1;
+            if neighbour == self.source {
+                continue;
+            }
+            let state = match self.policy.enter(&frame.state, neighbour) {
+                Some(state) => state,
+                None => continue,
+            };
+
+            let length = self.path.len() + 1;
+            if neighbour == self.sink {
+                if self.accepts_length(length) && self.policy.accepts(&state) {
+                    let mut path = self.path.clone();
+                    path.push(neighbour);
+                    return Some(path);
+                }
+            } else if self.can_extend_to(length) {
+                self.path.push(neighbour);
+                self.stack.push(Frame {
+                    state,
+                    next_neighbour: 0,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{count_paths, load_data, start_and_end, visit_policy::VisitLimits};
+
+    fn names(cave_system: &CaveSystem, paths: Vec<Vec<NodeId>>) -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| cave_system.names(path).join(","))
+            .collect()
+    }
+
+    #[test]
+    fn test_lexicographic_order() {
+        let data = load_data("./resources/test_data.txt");
+        let (start, end) = start_and_end(&data);
+        let policy = VisitLimits::small_caves_once(&data);
+
+        let paths = Paths::new(&data, start, end, &policy, PathOrder::Lexicographic)
+            .unwrap()
+            .map(|path| data.names(&path))
+            .collect::<Vec<_>>();
+
+        let mut sorted = paths.clone();
+        sorted.sort();
+        assert_eq!(paths.len(), 10);
+        assert_eq!(paths, sorted);
+    }
+
+    #[test]
+    fn test_shortest_first_order() {
+        let data = load_data("./resources/test_data.txt");
+        let (start, end) = start_and_end(&data);
+        let policy = VisitLimits::small_caves_once(&data);
+
+        let paths = Paths::new(&data, start, end, &policy, PathOrder::ShortestFirst)
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            names(&data, paths),
+            vec![
+                "start,A,end",
+                "start,b,end",
+                "start,A,b,end",
+                "start,b,A,end",
+                "start,A,b,A,end",
+                "start,A,c,A,end",
+                "start,A,c,A,b,end",
+                "start,b,A,c,A,end",
+                "start,A,b,A,c,A,end",
+                "start,A,c,A,b,A,end",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_orders_yield_every_path_once() {
+        let data = load_data("./resources/test_data_3.txt");
+        let (start, end) = start_and_end(&data);
+        let policy = VisitLimits::single_small_cave_twice(&data);
+
+        for order in [PathOrder::Lexicographic, PathOrder::ShortestFirst] {
+            let mut paths = Paths::new(&data, start, end, &policy, order)
+                .unwrap()
+                .collect::<Vec<_>>();
+            let expected = count_paths(&data, start, end, &policy).unwrap();
+            assert_eq!(paths.len(), expected);
+            paths.sort();
+            paths.dedup();
+            assert_eq!(paths.len(), expected);
+        }
+    }
+
+    #[test]
+    fn test_take_and_filter() {
+        let data = load_data("./resources/test_data_2.txt");
+        let (start, end) = start_and_end(&data);
+        let policy = VisitLimits::small_caves_once(&data);
+        let kj = data.node_id("kj").unwrap();
+
+        let paths = Paths::new(&data, start, end, &policy, PathOrder::ShortestFirst)
+            .unwrap()
+            .filter(|path| path.contains(&kj))
+            .take(2)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            names(&data, paths),
+            vec!["start,kj,HN,end", "start,kj,dc,end"]
+        );
+    }
+}
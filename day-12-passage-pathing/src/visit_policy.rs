@@ -0,0 +1,509 @@
+use std::{error::Error, fmt, hash::Hash};
+
+use common::graph::NodeId;
+
+use crate::cave::CaveSystem;
+
+// Decides which caves a path may enter. Path searches never return to their
+// source cave, everything else is up to the policy. The state is used as a
+// memoisation key when counting paths, so it should be small.
+pub trait VisitPolicy {
+    type State: Clone + Eq + Hash;
+
+    fn initial_state(&self, source: NodeId) -> Self::State;
+
+    // Returns the state after entering `cave`, or `None` if it can't be entered.
+    fn enter(&self, state: &Self::State, cave: NodeId) -> Option<Self::State>;
+
+    // Whether a path which reached the sink in `state` counts as a result.
+    fn accepts(&self, _state: &Self::State) -> bool {
+        true
+    }
+
+    // Checks, before any search runs, that the policy only allows finitely
+    // many paths and that its state can be represented.
+    fn validate(
+        &self,
+        _cave_system: &CaveSystem,
+        _source: NodeId,
+        _sink: NodeId,
+    ) -> Result<(), PolicyError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PolicyError {
+    // Both caves may be entered without limit, so paths can go back and forth
+    // between them forever.
+    UnboundedCycle(String, String),
+    // The visit counts of limited caves don't fit into the 64 bits of state.
+    TooManyVisitLimits { bits: u32 },
+    TooManyWaypoints(usize),
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::UnboundedCycle(a, b) => write!(
+                f,
+                "infinitely many paths, caves {} and {} can both be visited without limit",
+                a, b
+            ),
+            PolicyError::TooManyVisitLimits { bits } => write!(
+                f,
+                "visit limits need {} bits of state, at most {} are supported",
+                bits,
+                u64::BITS
+            ),
+            PolicyError::TooManyWaypoints(count) => write!(
+                f,
+                "{} waypoints given, at most {} are supported",
+                count,
+                u64::BITS
+            ),
+        }
+    }
+}
+
+impl Error for PolicyError {}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct VisitField {
+    shift: u32,
+    mask: u64,
+    limit: u64,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum RepeatState {
+    Available,
+    Active { cave: NodeId, visits: usize },
+    Exhausted,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct VisitLimitsState {
+    // Visit counts of limited caves, each saturated at the cave's limit. For
+    // caves which may be visited once this is a bitmask of visited caves.
+    visits: u64,
+    repeat: RepeatState,
+    waypoints: u64,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VisitLimits {
+    limits: Vec<Option<usize>>,
+    forbidden: Vec<bool>,
+    waypoints: Vec<NodeId>,
+    single_cave_repeat: Option<usize>,
+    fields: Vec<Option<VisitField>>,
+    // Total width of the visit count fields.
+    state_bits: u32,
+}
+
+impl VisitLimits {
+    // Takes visit limits from the cave attributes.
+    pub fn new(cave_system: &CaveSystem) -> Self {
+        let limits = cave_system
+            .nodes()
+            .map(|cave| cave_system.attributes(cave).visit_limit)
+            .collect();
+        Self {
+            limits,
+            forbidden: vec![false; cave_system.node_count()],
+            waypoints: Vec::new(),
+            single_cave_repeat: None,
+            fields: Vec::new(),
+            state_bits: 0,
+        }
+        .with_layout()
+    }
+
+    pub fn small_caves_once(cave_system: &CaveSystem) -> Self {
+        Self::new(cave_system)
+    }
+
+    pub fn single_small_cave_twice(cave_system: &CaveSystem) -> Self {
+        Self::new(cave_system).with_single_cave_repeat(1)
+    }
+
+    pub fn with_visit_limit(mut self, cave: NodeId, limit: Option<usize>) -> Self {
+        self.limits[cave] = limit;
+        self.with_layout()
+    }
+
+    // A single cave with a visit limit may be visited `extra` times more than
+    // its limit allows.
+    pub fn with_single_cave_repeat(mut self, extra: usize) -> Self {
+        self.single_cave_repeat = Some(extra);
+        self
+    }
+
+    pub fn with_forbidden(mut self, cave: NodeId) -> Self {
+        self.forbidden[cave] = true;
+        self
+    }
+
+    pub fn with_waypoint(mut self, cave: NodeId) -> Self {
+        if !self.waypoints.contains(&cave) {
+            self.waypoints.push(cave);
+        }
+        self
+    }
+
+    // Packs the visit counts into fields of a `u64`. Layouts wider than that
+    // are rejected by `validate`, before any of their fields are used.
+    fn with_layout(mut self) -> Self {
+        let mut shift = 0u32;
+        self.fields = self
+            .limits
+            .iter()
+            .map(|limit| {
+                limit.map(|limit| {
+                    let limit = limit as u64;
+                    let width = u64::BITS - limit.leading_zeros();
+                    let field = VisitField {
+                        shift,
+                        mask: 1u64.checked_shl(width).map_or(u64::MAX, |bit| bit - 1),
+                        limit,
+                    };
+                    shift = shift.saturating_add(width);
+                    field
+                })
+            })
+            .collect();
+        self.state_bits = shift;
+        self
+    }
+
+    fn waypoint_mask(&self, cave: NodeId) -> u64 {
+        self.waypoints
+            .iter()
+            .position(|waypoint| *waypoint == cave)
+            .map(|index| 1 << index)
+            .unwrap_or(0)
+    }
+
+    fn enter_repeated(
+        &self,
+        repeat: RepeatState,
+        cave: NodeId,
+        limit: usize,
+    ) -> Option<RepeatState> {
+        let times = limit.saturating_add(self.single_cave_repeat?);
+        let visits = match repeat {
+            RepeatState::Available => limit + 1,
+            RepeatState::Active {
+                cave: repeated_cave,
+                visits,
+            } if repeated_cave == cave => visits + 1,
+            _ => return None,
+        };
+        if visits > times {
+            None
+        } else if visits == times {
+            Some(RepeatState::Exhausted)
+        } else {
+            Some(RepeatState::Active { cave, visits })
+        }
+    }
+}
+
+impl VisitPolicy for VisitLimits {
+    type State = VisitLimitsState;
+
+    fn initial_state(&self, source: NodeId) -> Self::State {
+        VisitLimitsState {
+            visits: 0,
+            repeat: RepeatState::Available,
+            waypoints: 0,
+        }
+        .entered(self, source)
+    }
+
+    fn enter(&self, state: &Self::State, cave: NodeId) -> Option<Self::State> {
+        if self.forbidden[cave] {
+            return None;
+        }
+
+        let mut next_state = state.entered(self, cave);
+        if let Some(VisitField { shift, mask, limit }) = self.fields[cave] {
+            if (state.visits >> shift) & mask >= limit {
+                next_state.repeat = self.enter_repeated(state.repeat, cave, limit as usize)?;
+            }
+        }
+        Some(next_state)
+    }
+
+    fn accepts(&self, state: &Self::State) -> bool {
+        state.waypoints.count_ones() as usize == self.waypoints.len()
+    }
+
+    // Paths never return to the source and end at the sink, so only other
+    // caves can form a cycle.
+    fn validate(
+        &self,
+        cave_system: &CaveSystem,
+        source: NodeId,
+        sink: NodeId,
+    ) -> Result<(), PolicyError> {
+        if self.state_bits > u64::BITS {
+            return Err(PolicyError::TooManyVisitLimits {
+                bits: self.state_bits,
+            });
+        }
+        if self.waypoints.len() > u64::BITS as usize {
+            return Err(PolicyError::TooManyWaypoints(self.waypoints.len()));
+        }
+
+        let unlimited = |cave: NodeId| {
+            cave != source && cave != sink && !self.forbidden[cave] && self.limits[cave].is_none()
+        };
+        for cave in cave_system.nodes().filter(|cave| unlimited(*cave)) {
+            if let Some(&neighbour) = cave_system
+                .neighbours(cave)
+                .iter()
+                .find(|neighbour| unlimited(**neighbour))
+            {
+                return Err(PolicyError::UnboundedCycle(
+                    cave_system.name(cave).to_string(),
+                    cave_system.name(neighbour).to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl VisitLimitsState {
+    fn entered(mut self, policy: &VisitLimits, cave: NodeId) -> Self {
+        if let Some(VisitField { shift, mask, limit }) = policy.fields[cave] {
+            if (self.visits >> shift) & mask < limit {
+                self.visits += 1 << shift;
+            }
+        }
+        self.waypoints |= policy.waypoint_mask(cave);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        count_paths, find_paths, load_data,
+        paths_iterator::{PathOrder, Paths},
+        start_and_end,
+    };
+
+    #[test]
+    fn test_built_in_policies() {
+        let expected = [
+            ("./resources/test_data.txt", 10, 36),
+            ("./resources/test_data_2.txt", 19, 103),
+            ("./resources/test_data_3.txt", 226, 3509),
+        ];
+        for (file_name, part_1, part_2) in expected {
+            let data = load_data(file_name);
+            let (start, end) = start_and_end(&data);
+            let part_1_policy = VisitLimits::small_caves_once(&data);
+            let part_2_policy = VisitLimits::single_small_cave_twice(&data);
+            assert_eq!(
+                find_paths(&data, start, end, &part_1_policy, PathOrder::Lexicographic)
+                    .unwrap()
+                    .count(),
+                part_1
+            );
+            assert_eq!(
+                find_paths(&data, start, end, &part_2_policy, PathOrder::Lexicographic)
+                    .unwrap()
+                    .count(),
+                part_2
+            );
+        }
+    }
+
+    #[test]
+    fn test_forbidden_cave() {
+        let data = load_data("./resources/test_data.txt");
+        let (start, end) = start_and_end(&data);
+        let c = data.node_id("c").unwrap();
+        let policy = VisitLimits::small_caves_once(&data).with_forbidden(c);
+
+        let paths = find_paths(&data, start, end, &policy, PathOrder::Lexicographic)
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        assert_eq!(paths.len(), 5);
+        assert!(paths.iter().all(|path| !path.contains(&"c".to_string())));
+    }
+
+    #[test]
+    fn test_waypoint() {
+        let data = load_data("./resources/test_data.txt");
+        let (start, end) = start_and_end(&data);
+        let c = data.node_id("c").unwrap();
+        let policy = VisitLimits::small_caves_once(&data).with_waypoint(c);
+
+        let paths = find_paths(&data, start, end, &policy, PathOrder::Lexicographic)
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        assert_eq!(paths.len(), 5);
+        assert!(paths.iter().all(|path| path.contains(&"c".to_string())));
+    }
+
+    #[test]
+    fn test_per_cave_limit() {
+        let data = load_data("./resources/test_data.txt");
+        let (start, end) = start_and_end(&data);
+        let b = data.node_id("b").unwrap();
+        let a = data.node_id("A").unwrap();
+        let policy = VisitLimits::small_caves_once(&data)
+            .with_visit_limit(b, Some(2))
+            .with_visit_limit(a, Some(1));
+
+        let paths = find_paths(&data, start, end, &policy, PathOrder::Lexicographic)
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        assert_eq!(paths.len(), 8);
+        assert!(paths.iter().all(|path| {
+            path.iter().filter(|cave| *cave == "A").count() <= 1
+                && path.iter().filter(|cave| *cave == "b").count() <= 2
+        }));
+    }
+
+    #[test]
+    fn test_single_cave_repeat() {
+        let data = load_data("./resources/test_data.txt");
+        let (start, end) = start_and_end(&data);
+        let policy = VisitLimits::small_caves_once(&data).with_single_cave_repeat(2);
+
+        let paths = find_paths(&data, start, end, &policy, PathOrder::Lexicographic)
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        assert_eq!(count_paths(&data, start, end, &policy), Ok(paths.len()));
+        assert!(paths.iter().all(|path| {
+            let visits = |name: &str| path.iter().filter(|cave| *cave == name).count();
+            let repeated = ["b", "c", "d"]
+                .iter()
+                .filter(|cave| visits(cave) > 1)
+                .count();
+            repeated <= 1 && ["b", "c", "d"].iter().all(|cave| visits(cave) <= 3)
+        }));
+        assert!(paths
+            .iter()
+            .any(|path| path.iter().filter(|cave| *cave == "b").count() == 3));
+    }
+
+    #[test]
+    fn test_single_cave_repeat_above_limit() {
+        let data = load_data("./resources/test_data.txt");
+        let (start, end) = start_and_end(&data);
+        let b = data.node_id("b").unwrap();
+        let limited = VisitLimits::small_caves_once(&data).with_visit_limit(b, Some(2));
+        let policy = limited.clone().with_single_cave_repeat(1);
+
+        let paths = find_paths(&data, start, end, &policy, PathOrder::Lexicographic)
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        assert!(
+            paths.len()
+                > find_paths(&data, start, end, &limited, PathOrder::Lexicographic)
+                    .unwrap()
+                    .count()
+        );
+        assert!(paths
+            .iter()
+            .any(|path| path.iter().filter(|cave| *cave == "b").count() == 3));
+        assert!(paths
+            .iter()
+            .all(|path| path.iter().filter(|cave| *cave == "b").count() <= 3));
+    }
+
+    #[test]
+    fn test_unbounded_cycle() {
+        let data = load_data("./resources/test_data.txt");
+        let (start, end) = start_and_end(&data);
+        let b = data.node_id("b").unwrap();
+        let unlimited = VisitLimits::small_caves_once(&data).with_visit_limit(b, None);
+
+        assert_eq!(
+            count_paths(&data, start, end, &unlimited),
+            Err(PolicyError::UnboundedCycle(
+                "A".to_string(),
+                "b".to_string()
+            ))
+        );
+        assert!(Paths::new(&data, start, end, &unlimited, PathOrder::Lexicographic).is_err());
+        let a = data.node_id("A").unwrap();
+        let bounded = unlimited.with_visit_limit(a, Some(3));
+        assert_eq!(
+            count_paths(&data, start, end, &bounded),
+            Ok(
+                find_paths(&data, start, end, &bounded, PathOrder::Lexicographic)
+                    .unwrap()
+                    .count()
+            )
+        );
+    }
+
+    #[test]
+    fn test_too_many_visit_limits() {
+        let data = load_data("./resources/test_data.txt");
+        let (start, end) = start_and_end(&data);
+        let b = data.node_id("b").unwrap();
+        let c = data.node_id("c").unwrap();
+        let policy = VisitLimits::small_caves_once(&data)
+            .with_visit_limit(b, Some(usize::MAX))
+            .with_visit_limit(c, Some(1 << 40));
+
+        assert!(matches!(
+            count_paths(&data, start, end, &policy),
+            Err(PolicyError::TooManyVisitLimits { .. })
+        ));
+        // 61 bits for `b` and one for each of `start`, `d` and `end`.
+        let policy = VisitLimits::small_caves_once(&data)
+            .with_visit_limit(b, Some(1 << 60))
+            .with_visit_limit(c, Some(0));
+        assert_eq!(policy.validate(&data, start, end), Ok(()));
+    }
+
+    struct MaxSteps(usize);
+
+    impl VisitPolicy for MaxSteps {
+        type State = usize;
+
+        fn initial_state(&self, _source: NodeId) -> Self::State {
+            0
+        }
+
+        fn enter(&self, steps: &Self::State, _cave: NodeId) -> Option<Self::State> {
+            if *steps < self.0 {
+                Some(steps + 1)
+            } else {
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_policy() {
+        let data = load_data("./resources/test_data.txt");
+        let (start, end) = start_and_end(&data);
+
+        let paths = find_paths(&data, start, end, &MaxSteps(4), PathOrder::Lexicographic)
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        assert!(paths.iter().all(|path| path.len() <= 5));
+        assert_eq!(
+            count_paths(&data, start, end, &MaxSteps(4)),
+            Ok(paths.len())
+        );
+    }
+}
@@ -1,125 +1,217 @@
 use std::{
-    collections::BinaryHeap,
-    fs::File,
-    io::{BufRead, BufReader},
+    env,
+    fs::{self, File},
+    io::{self, BufWriter, IsTerminal, Write},
 };
 
-type RisksMap = Vec<Vec<usize>>;
+use common::{
+    grid::{parse_coordinates, Grid, ParseGridError},
+    pathfinding::{
+        self, a_star, manhattan_distance, Coordinates, Neighbourhood, Path, WeightedGrid,
+    },
+};
 
-fn load_data(file_name: &str) -> RisksMap {
-    let file = File::open(file_name).expect(&format!("Can't read file {}", file_name));
-    let file = BufReader::new(file);
-
-    file.lines()
-        .map(|line| {
-            line.unwrap()
-                .chars()
-                .map(|c| c.to_digit(10).unwrap() as usize)
-                .collect()
-        })
-        .collect()
-}
+mod render;
+mod tiled_risk_map;
 
-#[derive(Copy, Clone, Eq, PartialEq)]
-struct Node {
-    risk: usize,
-    coordinates: (usize, usize),
-}
+use render::RenderStyle;
+use tiled_risk_map::TiledRiskMap;
 
-impl Ord for Node {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        other.risk.cmp(&self.risk)
-    }
+type RisksMap = Grid<u8>;
+
+// Risks go from 1 to 9, a 0 is rejected as an invalid character.
+fn parse_risks(input: &str) -> Result<RisksMap, ParseGridError> {
+    Grid::parse_chars(input, |c| {
+        c.to_digit(10)
+            .filter(|risk| *risk > 0)
+            .map(|risk| risk as u8)
+    })
 }
 
-impl PartialOrd for Node {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
+fn load_data(file_name: &str) -> RisksMap {
+    let input =
+        fs::read_to_string(file_name).unwrap_or_else(|_| panic!("Can't read file {}", file_name));
+    parse_risks(&input).unwrap_or_else(|error| panic!("{}: {}", file_name, error))
 }
 
-fn get_adjacent(x: usize, y: usize, max_xy: usize) -> Vec<(usize, usize)> {
-    vec![
-        if x as i64 - 1 < 0 {
-            None
-        } else {
-            Some((x - 1, y))
-        },
-        if y as i64 - 1 < 0 {
-            None
-        } else {
-            Some((x, y - 1))
-        },
-        if x + 1 >= max_xy {
-            None
-        } else {
-            Some((x + 1, y))
-        },
-        if y + 1 >= max_xy {
-            None
-        } else {
-            Some((x, y + 1))
-        },
-    ]
-    .into_iter()
-    .filter_map(|v| v)
-    .collect::<Vec<(usize, usize)>>()
+// Manhattan distance only underestimates the remaining risk if every cell
+// costs at least 1, which custom transforms don't guarantee. Scaled by the
+// lowest risk on the map it stays admissible, down to plain Dijkstra for maps
+// with cells of risk 0.
+fn find_path<Map: WeightedGrid>(
+    risks_map: &Map,
+    from: Coordinates,
+    to: Coordinates,
+) -> Option<Path> {
+    let (x_size, y_size) = risks_map.size();
+    let min_risk = (0..x_size)
+        .flat_map(|x| (0..y_size).map(move |y| (x, y)))
+        .filter_map(|coordinates| risks_map.cost(coordinates))
+        .min()?;
+    a_star(risks_map, from, to, Neighbourhood::Four, |a, b| {
+        manhattan_distance(a, b) * min_risk
+    })
 }
 
-fn find_path_with_lowest_risk(data: &RisksMap, times: usize) -> Option<usize> {
-    let cave_size = data.len() * times;
+fn find_path_with_lowest_risk<Map: WeightedGrid>(risks_map: &Map) -> Option<Path> {
+    let (x_size, y_size) = risks_map.size();
+    if x_size == 0 || y_size == 0 {
+        return None;
+    }
 
-    let mut dist = vec![vec![std::usize::MAX; cave_size]; cave_size];
+    find_path(risks_map, (0, 0), (x_size - 1, y_size - 1))
+}
 
-    let mut remaining_nodes = BinaryHeap::new();
-    remaining_nodes.push(Node {
-        risk: 0,
-        coordinates: (0, 0),
-    });
+fn part_1_result(file_name: &str) {
+    let data = load_data(file_name);
+    let risk = find_path_with_lowest_risk(&TiledRiskMap::incrementing(&data, (1, 1)));
+    println!("Part 1. Result: {}", risk.unwrap().cost);
+}
 
-    while let Some(Node { risk, coordinates }) = remaining_nodes.pop() {
-        if coordinates == (cave_size - 1, cave_size - 1) {
-            return Some(risk);
-        }
+fn part_2_result(file_name: &str) {
+    let data = load_data(file_name);
+    let risk = find_path_with_lowest_risk(&TiledRiskMap::incrementing(&data, (5, 5)));
+    println!("Part 2. Result: {}", risk.unwrap().cost);
+}
 
-        if risk > dist[coordinates.0][coordinates.1] {
-            continue;
-        }
+#[derive(Default)]
+struct QueryOptions {
+    tiles: Option<(usize, usize)>,
+    from: Option<Coordinates>,
+    to: Option<Coordinates>,
+    k_paths: Option<usize>,
+    distances: bool,
+    show: bool,
+    plain: bool,
+    ppm: Option<String>,
+    scale: usize,
+}
 
-        for adjecent in get_adjacent(coordinates.0, coordinates.1, cave_size) {
-            let base_risk = data[adjecent.0 % data.len()][adjecent.1 % data.len()];
-            let new_risk =
-                (base_risk + ((adjecent.0 / data.len()) + (adjecent.1 / data.len())) - 1) % 9 + 1;
-            let new_node = Node {
-                risk: risk + new_risk,
-                coordinates: adjecent,
+impl QueryOptions {
+    fn from_args<I: Iterator<Item = String>>(mut args: I) -> Self {
+        let mut options = QueryOptions {
+            scale: 1,
+            ..Default::default()
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .unwrap_or_else(|| panic!("Missing value for {}", arg))
             };
-
-            if new_node.risk < dist[adjecent.0][adjecent.1] {
-                remaining_nodes.push(new_node);
-
-                dist[adjecent.0][adjecent.1] = new_node.risk;
+            match arg.as_str() {
+                "--tiles" => options.tiles = Some(parse_tiles(&value())),
+                "--from" => options.from = Some(coordinates_from_arg(&value())),
+                "--to" => options.to = Some(coordinates_from_arg(&value())),
+                "--k-paths" => options.k_paths = Some(value().parse().unwrap()),
+                "--distances" => options.distances = true,
+                "--show" => options.show = true,
+                "--plain" => options.plain = true,
+                "--ppm" => options.ppm = Some(value()),
+                "--scale" => {
+                    options.scale = value().parse().unwrap();
+                    if options.scale == 0 {
+                        panic!("--scale has to be at least 1");
+                    }
+                }
+                _ => panic!("Unknown argument {}", arg),
             }
         }
+        options
     }
 
-    None
+    fn any(&self) -> bool {
+        self.k_paths.is_some() || self.distances || self.show || self.ppm.is_some()
+    }
 }
 
-fn part_1_result(file_name: &str) {
-    let data = load_data(file_name);
-    let risk = find_path_with_lowest_risk(&data, 1);
-    println!("Part 1. Result: {}", risk.unwrap());
+fn coordinates_from_arg(coordinates: &str) -> Coordinates {
+    parse_coordinates(coordinates).unwrap_or_else(|error| panic!("{}", error))
 }
 
-fn part_2_result(file_name: &str) {
+// Either `n` for `n` x `n` tiles or `down`x`right`.
+fn parse_tiles(tiles: &str) -> (usize, usize) {
+    match tiles.split_once('x') {
+        Some((x_tiles, y_tiles)) => (x_tiles.parse().unwrap(), y_tiles.parse().unwrap()),
+        None => {
+            let tiles = tiles.parse().unwrap();
+            (tiles, tiles)
+        }
+    }
+}
+
+fn query_result(file_name: &str, options: &QueryOptions) {
     let data = load_data(file_name);
-    let risk = find_path_with_lowest_risk(&data, 5);
-    println!("Part 2. Result: {}", risk.unwrap());
+    let risks_map = TiledRiskMap::incrementing(&data, options.tiles.unwrap_or((1, 1)));
+    let (x_size, y_size) = risks_map.size();
+    let from = options.from.unwrap_or((0, 0));
+    let to = options
+        .to
+        .unwrap_or((x_size.saturating_sub(1), y_size.saturating_sub(1)));
+
+    if options.distances {
+        let distances = pathfinding::distance_field(&risks_map, from, Neighbourhood::Four);
+        let width = distances
+            .iter()
+            .flatten()
+            .max()
+            .map_or(1, |distance| distance.to_string().len());
+        for row in distances.rows() {
+            let row = row
+                .iter()
+                .map(|distance| match distance {
+                    Some(distance) => format!("{:>width$}", distance, width = width),
+                    None => format!("{:>width$}", "-", width = width),
+                })
+                .collect::<Vec<_>>();
+            println!("{}", row.join(" "));
+        }
+    }
+
+    let paths = match options.k_paths {
+        Some(k) => pathfinding::k_shortest_paths(&risks_map, from, to, Neighbourhood::Four, k),
+        None if options.show || options.ppm.is_some() => {
+            find_path(&risks_map, from, to).into_iter().collect()
+        }
+        None => Vec::new(),
+    };
+    let style = if !options.plain && io::stdout().is_terminal() {
+        RenderStyle::Ansi
+    } else {
+        RenderStyle::Plain
+    };
+    for (index, path) in paths.iter().enumerate() {
+        if options.k_paths.is_some() {
+            println!(
+                "{}. Total risk: {}, steps: {}",
+                index + 1,
+                path.cost,
+                path.cells.len() - 1
+            );
+        }
+        if options.show {
+            let mut output = BufWriter::new(io::stdout().lock());
+            render::write_path_grid(&risks_map, path, style, &mut output).unwrap();
+            output.flush().unwrap();
+        }
+    }
+
+    if let Some(ppm) = &options.ppm {
+        let path = paths.first().expect("No path found");
+        let file = File::create(ppm).unwrap_or_else(|_| panic!("Can't create file {}", ppm));
+        let mut output = BufWriter::new(file);
+        render::write_path_ppm(&risks_map, path, options.scale, &mut output).unwrap();
+        output.flush().unwrap();
+        println!("Exported path with total risk {} to {}", path.cost, ppm);
+    }
 }
 
 fn main() {
     const DATA_FILENAME: &str = "./resources/data.txt";
+    let query_options = QueryOptions::from_args(env::args().skip(1));
+    if query_options.any() {
+        query_result(DATA_FILENAME, &query_options);
+        return;
+    }
     part_1_result(DATA_FILENAME);
     part_2_result(DATA_FILENAME);
 }
@@ -132,15 +224,77 @@ mod tests {
     fn part_1_test_data() {
         const TEST_DATA_FILENAME: &str = "./resources/test_data.txt";
         let data = load_data(TEST_DATA_FILENAME);
-        let risk = find_path_with_lowest_risk(&data, 1).unwrap();
-        assert_eq!(risk, 40);
+        let path = find_path_with_lowest_risk(&data).unwrap();
+        assert_eq!(path.cost, 40);
+        assert_eq!(path.cells.first(), Some(&(0, 0)));
+        assert_eq!(path.cells.last(), Some(&(9, 9)));
     }
 
     #[test]
     fn part_2_test_data() {
         const TEST_DATA_FILENAME: &str = "./resources/test_data.txt";
         let data = load_data(TEST_DATA_FILENAME);
-        let risk = find_path_with_lowest_risk(&data, 5).unwrap();
-        assert_eq!(risk, 315);
+        let path = find_path_with_lowest_risk(&TiledRiskMap::incrementing(&data, (5, 5))).unwrap();
+        assert_eq!(path.cost, 315);
+    }
+
+    #[test]
+    fn zero_risk_is_rejected() {
+        assert_eq!(
+            parse_risks("19\n10"),
+            Err(ParseGridError::InvalidCharacter {
+                x: 1,
+                y: 1,
+                character: '0'
+            })
+        );
+        assert_eq!(parse_risks("19\n91").unwrap().size(), (2, 2));
+    }
+
+    #[test]
+    fn rectangular_map() {
+        let data = Grid::from_rows(vec![vec![1, 9, 1], vec![1, 1, 1]]).unwrap();
+        let risks_map = TiledRiskMap::incrementing(&data, (2, 3));
+        let path = find_path_with_lowest_risk(&risks_map).unwrap();
+        assert_eq!(path.cells.last(), Some(&(3, 8)));
+        let cost = path.cells[1..]
+            .iter()
+            .map(|cell| risks_map.cost(*cell).unwrap())
+            .sum::<usize>();
+        assert_eq!(cost, path.cost);
+    }
+
+    #[test]
+    fn part_2_test_data_matches_enlarged_map() {
+        const TEST_DATA_FILENAME: &str = "./resources/test_data.txt";
+        let data = load_data(TEST_DATA_FILENAME);
+        let risks_map = TiledRiskMap::incrementing(&data, (5, 5));
+        let (x_size, y_size) = risks_map.size();
+        let enlarged = Grid::from_fn(y_size, x_size, |coordinates| {
+            risks_map.cost(coordinates).unwrap()
+        });
+        assert_eq!(find_path_with_lowest_risk(&enlarged).unwrap().cost, 315);
+    }
+
+    #[test]
+    fn transform_with_zero_risks() {
+        let data = parse_risks("19111\n19191\n11121").unwrap();
+        // Winding around the nines is free, going straight along the bottom
+        // costs 1.
+        let risks_map = TiledRiskMap::new(&data, (1, 1), |risk, _| risk - 1);
+        let path = find_path_with_lowest_risk(&risks_map).unwrap();
+        let expected = pathfinding::dijkstra(&risks_map, (0, 0), (2, 4), Neighbourhood::Four);
+        assert_eq!(path.cost, 0);
+        assert_eq!(Some(path), expected);
+    }
+
+    #[test]
+    fn k_lowest_risk_paths() {
+        const TEST_DATA_FILENAME: &str = "./resources/test_data.txt";
+        let data = load_data(TEST_DATA_FILENAME);
+        let paths = pathfinding::k_shortest_paths(&data, (0, 0), (9, 9), Neighbourhood::Four, 3);
+        assert_eq!(paths.len(), 3);
+        assert_eq!(paths[0].cost, 40);
+        assert!(paths.windows(2).all(|pair| pair[0].cost <= pair[1].cost));
     }
 }
@@ -0,0 +1,164 @@
+use std::io::{self, Write};
+
+use common::{
+    grid::{Coordinates, Grid},
+    pathfinding::{Path, WeightedGrid},
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RenderStyle {
+    // Path risks in bold red, the rest dimmed.
+    Ansi,
+    // Path risks marked with a `*` in front of them.
+    Plain,
+}
+
+// Cells which can't be entered have no risk to draw.
+fn risk<Map: WeightedGrid>(risks_map: &Map, (x, y): Coordinates) -> io::Result<usize> {
+    risks_map.cost((x, y)).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Cell {},{} has no risk", x, y),
+        )
+    })
+}
+
+fn path_mask<Map: WeightedGrid>(risks_map: &Map, path: &Path) -> Grid<bool> {
+    let (x_size, y_size) = risks_map.size();
+    let mut mask = Grid::new(y_size, x_size, false);
+    for cell in &path.cells {
+        mask[*cell] = true;
+    }
+    mask
+}
+
+pub fn write_path_grid<Map: WeightedGrid, W: Write>(
+    risks_map: &Map,
+    path: &Path,
+    style: RenderStyle,
+    writer: &mut W,
+) -> io::Result<()> {
+    for (x, row) in path_mask(risks_map, path).rows().enumerate() {
+        for (y, on_path) in row.iter().enumerate() {
+            let risk = risk(risks_map, (x, y))?;
+            match (style, on_path) {
+                (RenderStyle::Ansi, true) => write!(writer, "\x1b[1;31m{}\x1b[0m", risk)?,
+                (RenderStyle::Ansi, false) => write!(writer, "\x1b[2m{}\x1b[0m", risk)?,
+                (RenderStyle::Plain, true) => write!(writer, "*{}", risk)?,
+                (RenderStyle::Plain, false) => write!(writer, " {}", risk)?,
+            }
+        }
+        writeln!(writer)?;
+    }
+    writeln!(writer, "Total risk: {}", path.cost)
+}
+
+// Binary PPM, as text images of fully tiled maps get unwieldy. Every cell is a
+// `scale` x `scale` square, grey getting darker with higher risk, the path red.
+pub fn write_path_ppm<Map: WeightedGrid, W: Write>(
+    risks_map: &Map,
+    path: &Path,
+    scale: usize,
+    writer: &mut W,
+) -> io::Result<()> {
+    let (x_size, y_size) = risks_map.size();
+    writeln!(writer, "P6")?;
+    writeln!(writer, "# total risk {}", path.cost)?;
+    writeln!(writer, "{} {}", y_size * scale, x_size * scale)?;
+    writeln!(writer, "255")?;
+    for (x, row) in path_mask(risks_map, path).rows().enumerate() {
+        let mut pixels = Vec::with_capacity(row.len() * scale * 3);
+        for (y, on_path) in row.iter().enumerate() {
+            let colour = if *on_path {
+                [220, 20, 60]
+            } else {
+                let risk = risk(risks_map, (x, y))?.min(9) as u8;
+                let grey = 255 - risk * 24;
+                [grey, grey, grey]
+            };
+            pixels.extend(std::iter::repeat_n(colour, scale).flatten());
+        }
+        for _ in 0..scale {
+            writer.write_all(&pixels)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path() -> (Grid<u8>, Path) {
+        let risks_map = Grid::from_rows(vec![vec![1, 9], vec![2, 3]]).unwrap();
+        let path = Path {
+            cost: 5,
+            cells: vec![(0, 0), (1, 0), (1, 1)],
+        };
+        (risks_map, path)
+    }
+
+    #[test]
+    fn test_write_plain_grid() {
+        let (risks_map, path) = path();
+        let mut output = Vec::new();
+
+        write_path_grid(&risks_map, &path, RenderStyle::Plain, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "*1 9\n*2*3\nTotal risk: 5\n"
+        );
+    }
+
+    #[test]
+    fn test_write_ansi_grid() {
+        let (risks_map, path) = path();
+        let mut output = Vec::new();
+
+        write_path_grid(&risks_map, &path, RenderStyle::Ansi, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("\x1b[1;31m1\x1b[0m\x1b[2m9\x1b[0m\n"));
+    }
+
+    // The path's map with the top right cell walled off.
+    struct Walled;
+
+    impl WeightedGrid for Walled {
+        fn size(&self) -> (usize, usize) {
+            (2, 2)
+        }
+
+        fn cost(&self, coordinates: Coordinates) -> Option<usize> {
+            (coordinates != (0, 1)).then_some(1)
+        }
+    }
+
+    #[test]
+    fn test_cell_without_risk() {
+        let (_, path) = path();
+        let mut output = Vec::new();
+
+        let error = write_path_grid(&Walled, &path, RenderStyle::Plain, &mut output).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "Cell 0,1 has no risk");
+        assert!(write_path_ppm(&Walled, &path, 1, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let (risks_map, path) = path();
+        let mut output = Vec::new();
+
+        write_path_ppm(&risks_map, &path, 2, &mut output).unwrap();
+
+        let header = b"P6\n# total risk 5\n4 4\n255\n";
+        assert!(output.starts_with(header));
+        let pixels = &output[header.len()..];
+        assert_eq!(pixels.len(), 4 * 4 * 3);
+        // Top right cell is off the path with risk 9.
+        assert_eq!(&pixels[2 * 3..3 * 3], &[39, 39, 39]);
+        assert_eq!(&pixels[0..3], &[220, 20, 60]);
+    }
+}
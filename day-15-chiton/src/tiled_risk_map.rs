@@ -0,0 +1,96 @@
+use common::pathfinding::{Coordinates, WeightedGrid};
+
+use crate::RisksMap;
+
+// Risk of the original map increased by one for every tile to the right or
+// down, wrapping from 9 back to 1. Risks start at 1, as `parse_risks` ensures.
+pub fn increment_wrapping(risk: usize, (tile_x, tile_y): (usize, usize)) -> usize {
+    (risk + tile_x + tile_y - 1) % 9 + 1
+}
+
+// The risk map repeated `x_tiles` times down and `y_tiles` times to the right.
+// Risks are computed on demand from the original map, so the enlarged map is
+// never built.
+pub struct TiledRiskMap<'a, Transform> {
+    data: &'a RisksMap,
+    x_tiles: usize,
+    y_tiles: usize,
+    transform: Transform,
+}
+
+impl<'a, Transform> TiledRiskMap<'a, Transform>
+where
+    Transform: Fn(usize, (usize, usize)) -> usize,
+{
+    pub fn new(
+        data: &'a RisksMap,
+        (x_tiles, y_tiles): (usize, usize),
+        transform: Transform,
+    ) -> Self {
+        Self {
+            data,
+            x_tiles,
+            y_tiles,
+            transform,
+        }
+    }
+}
+
+impl<'a> TiledRiskMap<'a, fn(usize, (usize, usize)) -> usize> {
+    pub fn incrementing(data: &'a RisksMap, tiles: (usize, usize)) -> Self {
+        Self::new(data, tiles, increment_wrapping)
+    }
+}
+
+impl<Transform> WeightedGrid for TiledRiskMap<'_, Transform>
+where
+    Transform: Fn(usize, (usize, usize)) -> usize,
+{
+    fn size(&self) -> (usize, usize) {
+        let (x_size, y_size) = self.data.size();
+        (x_size * self.x_tiles, y_size * self.y_tiles)
+    }
+
+    fn cost(&self, (x, y): Coordinates) -> Option<usize> {
+        let (x_size, y_size) = self.data.size();
+        if x >= x_size * self.x_tiles || y >= y_size * self.y_tiles {
+            return None;
+        }
+        let risk = self.data.cost((x % x_size, y % y_size))?;
+        Some((self.transform)(risk, (x / x_size, y / y_size)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::grid::Grid;
+
+    #[test]
+    fn test_incrementing_tiles() {
+        let data = Grid::from_rows(vec![vec![8, 9]]).unwrap();
+        let risks_map = TiledRiskMap::incrementing(&data, (2, 3));
+
+        assert_eq!(risks_map.size(), (2, 6));
+        let risks = (0..2)
+            .map(|x| {
+                (0..6)
+                    .map(|y| risks_map.cost((x, y)).unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(risks, vec![vec![8, 9, 9, 1, 1, 2], vec![9, 1, 1, 2, 2, 3]]);
+        assert_eq!(risks_map.cost((2, 0)), None);
+        assert_eq!(risks_map.cost((0, 6)), None);
+    }
+
+    #[test]
+    fn test_custom_transform() {
+        let data = Grid::from_rows(vec![vec![1, 2], vec![3, 4]]).unwrap();
+        let risks_map = TiledRiskMap::new(&data, (1, 2), |risk, (_, tile_y)| risk * (tile_y + 1));
+
+        assert_eq!(risks_map.size(), (2, 4));
+        assert_eq!(risks_map.cost((1, 1)), Some(4));
+        assert_eq!(risks_map.cost((1, 3)), Some(8));
+    }
+}
@@ -0,0 +1,227 @@
+use common::automaton::{LookupTable, PlaneCoordinates};
+
+use crate::{Image, LitPixels};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+// Same image as `Image`, with every row packed into 64-bit words, bit `y % 64`
+// of word `y / 64` being the pixel in column `y`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BitImage {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+    background: u8,
+    origin: PlaneCoordinates,
+}
+
+impl BitImage {
+    fn empty(width: usize, height: usize, background: u8, origin: PlaneCoordinates) -> Self {
+        let words_per_row = width.div_ceil(WORD_BITS);
+        Self {
+            width,
+            height,
+            words_per_row,
+            words: vec![0; words_per_row * height],
+            background,
+            origin,
+        }
+    }
+
+    fn row(&self, x: isize) -> Option<&[u64]> {
+        if x < 0 || x as usize >= self.height {
+            return None;
+        }
+        let start = x as usize * self.words_per_row;
+        Some(&self.words[start..start + self.words_per_row])
+    }
+
+    fn set(&mut self, (x, y): (usize, usize)) {
+        self.words[x * self.words_per_row + y / WORD_BITS] |= 1 << (y % WORD_BITS);
+    }
+
+    #[cfg(test)]
+    pub fn pixel(&self, (x, y): (usize, usize)) -> u8 {
+        (self.words[x * self.words_per_row + y / WORD_BITS] >> (y % WORD_BITS)) as u8 & 1
+    }
+
+    // Row `x` shifted right by two columns, with background pixels in the two
+    // columns added on both sides. Rows outside of the image are all background.
+    fn padded_row(&self, x: isize) -> Vec<u64> {
+        let width = self.width + 4;
+        let mut padded = vec![0; width.div_ceil(WORD_BITS)];
+        match self.row(x) {
+            Some(row) => {
+                for (index, word) in row.iter().enumerate() {
+                    padded[index] |= word << 2;
+                    if let Some(next) = padded.get_mut(index + 1) {
+                        *next |= word >> (WORD_BITS - 2);
+                    }
+                }
+                if self.background == 1 {
+                    for y in [0, 1, width - 2, width - 1] {
+                        padded[y / WORD_BITS] |= 1 << (y % WORD_BITS);
+                    }
+                }
+            }
+            None if self.background == 1 => padded.fill(!0),
+            None => {}
+        }
+        padded
+    }
+
+    // The image grows by one pixel on every side. Pixel `(x, y)` of the result
+    // is centred on pixel `(x - 1, y - 1)` of this image, its index is built by
+    // sliding a 3x3 window along the three padded rows around it: shifting
+    // drops the leftmost column and the column entering on the right is added.
+    pub fn enhance(&self, lookup_table: &LookupTable) -> Self {
+        let background = if self.background == 1 {
+            lookup_table.get(0b1_1111_1111)
+        } else {
+            lookup_table.get(0)
+        };
+        let origin = (self.origin.0 - 1, self.origin.1 - 1);
+        let mut result = Self::empty(self.width + 2, self.height + 2, background, origin);
+
+        let mut rows = [self.padded_row(-2), self.padded_row(-1), self.padded_row(0)];
+        for x in 0..result.height {
+            if x > 0 {
+                rows.rotate_left(1);
+                rows[2] = self.padded_row(x as isize);
+            }
+            let column = |y: usize| {
+                let (word, bit) = (y / WORD_BITS, y % WORD_BITS);
+                let pixel = |row: &[u64]| (row[word] >> bit) as usize & 1;
+                (pixel(&rows[0]) << 6) | (pixel(&rows[1]) << 3) | pixel(&rows[2])
+            };
+
+            let words = &mut result.words[x * result.words_per_row..(x + 1) * result.words_per_row];
+            let mut index = (column(0) << 1) | column(1);
+            for y in 0..result.width {
+                index = ((index << 1) & 0b110_110_110) | column(y + 2);
+                words[y / WORD_BITS] |= (lookup_table.get(index) as u64) << (y % WORD_BITS);
+            }
+        }
+
+        result
+    }
+
+    pub fn enhance_n_times(&self, lookup_table: &LookupTable, n: usize) -> Self {
+        let mut image = self.clone();
+        for _ in 0..n {
+            image = image.enhance(lookup_table);
+        }
+        image
+    }
+
+    pub fn count_lit_pixels(&self) -> LitPixels {
+        if self.background == 0 {
+            LitPixels::Finite(
+                self.words
+                    .iter()
+                    .map(|word| word.count_ones() as usize)
+                    .sum(),
+            )
+        } else {
+            LitPixels::Infinite
+        }
+    }
+
+    #[cfg(test)]
+    pub fn to_image(&self) -> Image {
+        Image {
+            pixels: common::grid::Grid::from_fn(self.width, self.height, |coordinates| {
+                self.pixel(coordinates)
+            }),
+            background: self.background,
+            origin: self.origin,
+        }
+    }
+}
+
+impl From<&Image> for BitImage {
+    fn from(image: &Image) -> Self {
+        let mut result = Self::empty(
+            image.pixels.width(),
+            image.pixels.height(),
+            image.background,
+            image.origin,
+        );
+        for (coordinates, pixel) in image.pixels.cells() {
+            if *pixel == 1 {
+                result.set(coordinates);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{load_data, solver::SetImage, ImageWithEnhanceAlgorithm};
+
+    #[test]
+    fn test_matches_image() {
+        let ImageWithEnhanceAlgorithm {
+            mut image,
+            enhance_algorithm: algorithm,
+        } = load_data("resources/test_data.txt");
+        let mut bit_image = BitImage::from(&image);
+
+        for _ in 0..10 {
+            image = image.process_with_algorithm(&algorithm);
+            bit_image = bit_image.enhance(&algorithm.lookup_table);
+            assert_eq!(bit_image.to_image(), image);
+            assert_eq!(bit_image.count_lit_pixels(), image.count_lit_pixels());
+        }
+    }
+
+    #[test]
+    fn test_flipping_background() {
+        let ImageWithEnhanceAlgorithm {
+            image,
+            enhance_algorithm: algorithm,
+        } = load_data("resources/test_data_flipping.txt");
+        let bit_image = BitImage::from(&image).enhance_n_times(&algorithm.lookup_table, 3);
+
+        assert_eq!(bit_image.count_lit_pixels(), LitPixels::Infinite);
+        assert_eq!(
+            bit_image.to_image(),
+            image.process_with_algorithm_n_times(&algorithm, 3)
+        );
+    }
+
+    // Run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn benchmark_enhancement() {
+        let ImageWithEnhanceAlgorithm {
+            image,
+            enhance_algorithm: algorithm,
+        } = load_data("resources/data.txt");
+
+        let start = Instant::now();
+        let expected = SetImage::from_image(&image)
+            .process_with_algorithm_n_times(&algorithm, 50)
+            .count_lit_pixels();
+        let set_time = start.elapsed();
+
+        let start = Instant::now();
+        let lit_pixels = BitImage::from(&image)
+            .enhance_n_times(&algorithm.lookup_table, 50)
+            .count_lit_pixels();
+        let bit_image_time = start.elapsed();
+
+        assert_eq!(lit_pixels, LitPixels::Finite(expected.unwrap()));
+        println!(
+            "50 steps: sets {:?}, bit image {:?} ({:.1}x faster)",
+            set_time,
+            bit_image_time,
+            set_time.as_secs_f64() / bit_image_time.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,456 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use common::{
+    automaton::PlaneCoordinates,
+    grid::{parse_coordinates, Grid, ParseGridError},
+};
+
+use crate::{Image, Window};
+
+// PBM keeps one bit per pixel, 1 standing for black, so lit pixels come out
+// black on white in image viewers.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PbmFormat {
+    // P1, ASCII digits.
+    Plain,
+    // P4, rows packed into bytes, most significant bit first.
+    Raw,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ImageFormat {
+    // `#` for lit pixels and `.` for dark ones, as in the puzzle input.
+    Text,
+    Pbm(PbmFormat),
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "text" => Ok(ImageFormat::Text),
+            "p1" => Ok(ImageFormat::Pbm(PbmFormat::Plain)),
+            "p4" => Ok(ImageFormat::Pbm(PbmFormat::Raw)),
+            _ => Err(format!("Unknown image format {}", format)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(io::Error),
+    Text(ParseGridError),
+    Pbm(String),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Io(error) => write!(f, "{}", error),
+            ImageError::Text(error) => write!(f, "{}", error),
+            ImageError::Pbm(error) => write!(f, "invalid PBM: {}", error),
+        }
+    }
+}
+
+impl Error for ImageError {}
+
+impl From<io::Error> for ImageError {
+    fn from(error: io::Error) -> Self {
+        ImageError::Io(error)
+    }
+}
+
+impl From<ParseGridError> for ImageError {
+    fn from(error: ParseGridError) -> Self {
+        ImageError::Text(error)
+    }
+}
+
+impl Image {
+    // Plane coordinates of the first and the last stored pixel.
+    pub fn bounds(&self) -> Window {
+        let (x_size, y_size) = self.pixels.size();
+        let (x_origin, y_origin) = self.origin;
+        (
+            self.origin,
+            (
+                x_origin + x_size as isize - 1,
+                y_origin + y_size as isize - 1,
+            ),
+        )
+    }
+
+    // The pixels inside the window, which may extend past the stored pixels
+    // into the background.
+    pub fn crop(&self, ((x_min, y_min), (x_max, y_max)): Window) -> Image {
+        let span = |min: isize, max: isize| (max - min + 1).max(0) as usize;
+        let pixels = Grid::from_fn(span(y_min, y_max), span(x_min, x_max), |(x, y)| {
+            self.pixel((x_min + x as isize, y_min + y as isize))
+        });
+        Image {
+            pixels,
+            background: self.background,
+            origin: (x_min, y_min),
+        }
+    }
+
+    // Dark background, the first pixel at `(0, 0)`.
+    pub fn parse_text(input: &str) -> Result<Image, ParseGridError> {
+        let pixels = Grid::parse_chars(input, |c| match c {
+            '.' => Some(0),
+            '#' => Some(1),
+            _ => None,
+        })?;
+        Ok(Image {
+            pixels,
+            background: 0,
+            origin: (0, 0),
+        })
+    }
+
+    // The origin and the background are read back from the `# origin x,y` and
+    // `# background 0|1` comments written by `write_pbm`, `(0, 0)` and dark
+    // without them.
+    pub fn parse_pbm(input: &[u8]) -> Result<Image, ImageError> {
+        let mut tokens = PbmTokens {
+            input,
+            position: 0,
+            origin: None,
+            background: None,
+        };
+        let magic = tokens.next_token()?;
+        let width = tokens.next_number()?;
+        let height = tokens.next_number()?;
+
+        let bits = match magic {
+            b"P1" => (0..width * height)
+                .map(|_| match tokens.next_digit()? {
+                    b'0' => Ok(0),
+                    b'1' => Ok(1),
+                    digit => Err(ImageError::Pbm(format!(
+                        "unexpected pixel '{}'",
+                        digit as char
+                    ))),
+                })
+                .collect::<Result<Vec<u8>, _>>()?,
+            b"P4" => {
+                // A single whitespace character separates the header from the
+                // raster.
+                let raster = input.get(tokens.position + 1..).unwrap_or_default();
+                let row_bytes = width.div_ceil(8);
+                if raster.len() < row_bytes * height {
+                    return Err(ImageError::Pbm(format!(
+                        "expected {} bytes of pixels, found {}",
+                        row_bytes * height,
+                        raster.len()
+                    )));
+                }
+                (0..height)
+                    .flat_map(|x| {
+                        (0..width).map(move |y| (raster[x * row_bytes + y / 8] >> (7 - y % 8)) & 1)
+                    })
+                    .collect()
+            }
+            _ => {
+                return Err(ImageError::Pbm(format!(
+                    "unsupported format {}",
+                    String::from_utf8_lossy(magic)
+                )))
+            }
+        };
+
+        Ok(Image {
+            pixels: Grid::from_fn(width, height, |(x, y)| bits[x * width + y]),
+            background: tokens.background.unwrap_or(0),
+            origin: tokens.origin.unwrap_or((0, 0)),
+        })
+    }
+
+    // Picks the format from the magic number, anything else being text.
+    pub fn read(input: &[u8]) -> Result<Image, ImageError> {
+        if input.starts_with(b"P1") || input.starts_with(b"P4") {
+            return Image::parse_pbm(input);
+        }
+        let input = String::from_utf8(input.to_vec())
+            .map_err(|error| ImageError::Io(io::Error::new(io::ErrorKind::InvalidData, error)))?;
+        Ok(Image::parse_text(&input)?)
+    }
+
+    pub fn write_text<W: Write>(&self, viewport: Window, writer: &mut W) -> io::Result<()> {
+        let image = self.crop(viewport);
+        for row in image.pixels.rows() {
+            let row = row
+                .iter()
+                .map(|pixel| if *pixel == 1 { '#' } else { '.' })
+                .collect::<String>();
+            writeln!(writer, "{}", row)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_pbm<W: Write>(
+        &self,
+        viewport: Window,
+        format: PbmFormat,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        let image = self.crop(viewport);
+        let (height, width) = image.pixels.size();
+        let magic = match format {
+            PbmFormat::Plain => "P1",
+            PbmFormat::Raw => "P4",
+        };
+        writeln!(writer, "{}", magic)?;
+        writeln!(writer, "# origin {},{}", image.origin.0, image.origin.1)?;
+        writeln!(writer, "# background {}", image.background)?;
+        writeln!(writer, "{} {}", width, height)?;
+
+        for row in image.pixels.rows() {
+            match format {
+                // Lines of PBM files should stay under 70 characters.
+                PbmFormat::Plain => {
+                    for chunk in row.chunks(70) {
+                        let line = chunk
+                            .iter()
+                            .map(|pixel| (b'0' + pixel) as char)
+                            .collect::<String>();
+                        writeln!(writer, "{}", line)?;
+                    }
+                }
+                PbmFormat::Raw => {
+                    let bytes = row
+                        .chunks(8)
+                        .map(|chunk| {
+                            chunk
+                                .iter()
+                                .enumerate()
+                                .fold(0u8, |byte, (bit, pixel)| byte | (pixel << (7 - bit)))
+                        })
+                        .collect::<Vec<_>>();
+                    writer.write_all(&bytes)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write<W: Write>(
+        &self,
+        viewport: Window,
+        format: ImageFormat,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        match format {
+            ImageFormat::Text => self.write_text(viewport, writer),
+            ImageFormat::Pbm(format) => self.write_pbm(viewport, format, writer),
+        }
+    }
+}
+
+// Header tokens and plain pixels of a PBM file, skipping whitespace and
+// comments.
+struct PbmTokens<'a> {
+    input: &'a [u8],
+    position: usize,
+    // From an `origin x,y` comment.
+    origin: Option<PlaneCoordinates>,
+    // From a `background 0|1` comment.
+    background: Option<u8>,
+}
+
+impl<'a> PbmTokens<'a> {
+    fn skip_whitespace(&mut self) -> Result<(), ImageError> {
+        while let Some(byte) = self.input.get(self.position) {
+            match byte {
+                b'#' => {
+                    let start = self.position + 1;
+                    while self
+                        .input
+                        .get(self.position)
+                        .is_some_and(|byte| *byte != b'\n')
+                    {
+                        self.position += 1;
+                    }
+                    self.comment(start)?;
+                }
+                byte if byte.is_ascii_whitespace() => self.position += 1,
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn comment(&mut self, start: usize) -> Result<(), ImageError> {
+        let comment = String::from_utf8_lossy(&self.input[start..self.position]);
+        let comment = comment.trim();
+        if let Some(origin) = comment.strip_prefix("origin ") {
+            let origin = parse_coordinates(origin)
+                .map_err(|error| ImageError::Pbm(format!("invalid origin: {}", error)))?;
+            self.origin = Some(origin);
+        } else if let Some(background) = comment.strip_prefix("background ") {
+            self.background = match background {
+                "0" => Some(0),
+                "1" => Some(1),
+                _ => {
+                    return Err(ImageError::Pbm(format!(
+                        "invalid background {}",
+                        background
+                    )))
+                }
+            };
+        }
+        Ok(())
+    }
+
+    fn next_token(&mut self) -> Result<&'a [u8], ImageError> {
+        self.skip_whitespace()?;
+        let start = self.position;
+        while self
+            .input
+            .get(self.position)
+            .is_some_and(|byte| !byte.is_ascii_whitespace())
+        {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(ImageError::Pbm("unexpected end of file".to_string()));
+        }
+        Ok(&self.input[start..self.position])
+    }
+
+    fn next_number(&mut self) -> Result<usize, ImageError> {
+        let token = self.next_token()?;
+        String::from_utf8_lossy(token).parse().map_err(|_| {
+            ImageError::Pbm(format!("invalid size {}", String::from_utf8_lossy(token)))
+        })
+    }
+
+    // Plain pixels need no whitespace between them.
+    fn next_digit(&mut self) -> Result<u8, ImageError> {
+        self.skip_whitespace()?;
+        let digit = *self
+            .input
+            .get(self.position)
+            .ok_or_else(|| ImageError::Pbm("unexpected end of file".to_string()))?;
+        self.position += 1;
+        Ok(digit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_data;
+
+    const TEXT: &str = "#..#.\n#....\n##..#\n";
+
+    fn write(image: &Image, viewport: Window, format: ImageFormat) -> Vec<u8> {
+        let mut output = Vec::new();
+        image.write(viewport, format, &mut output).unwrap();
+        output
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let image = Image::parse_text(TEXT).unwrap();
+
+        let output = write(&image, image.bounds(), ImageFormat::Text);
+
+        assert_eq!(String::from_utf8(output.clone()).unwrap(), TEXT);
+        assert_eq!(Image::read(&output).unwrap(), image);
+    }
+
+    #[test]
+    fn test_pbm_round_trip() {
+        let image = Image::parse_text(TEXT).unwrap();
+
+        let plain = write(&image, image.bounds(), ImageFormat::Pbm(PbmFormat::Plain));
+        let raw = write(&image, image.bounds(), ImageFormat::Pbm(PbmFormat::Raw));
+
+        assert_eq!(
+            String::from_utf8(plain.clone()).unwrap(),
+            "P1\n# origin 0,0\n# background 0\n5 3\n10010\n10000\n11001\n"
+        );
+        assert!(raw.ends_with(&[0b1001_0000, 0b1000_0000, 0b1100_1000]));
+        assert_eq!(Image::read(&plain).unwrap(), image);
+        assert_eq!(Image::read(&raw).unwrap(), image);
+    }
+
+    #[test]
+    fn test_pbm_round_trip_with_origin() {
+        let image = Image::parse_text(TEXT).unwrap();
+        let viewport = ((-2, 3), (1, 6));
+
+        for format in [PbmFormat::Plain, PbmFormat::Raw] {
+            let output = write(&image, viewport, ImageFormat::Pbm(format));
+
+            let read = Image::read(&output).unwrap();
+            assert_eq!(read.origin, (-2, 3));
+            assert_eq!(read, image.crop(viewport));
+        }
+        assert!(matches!(
+            Image::parse_pbm(b"P1\n# origin 1;2\n1 1\n1"),
+            Err(ImageError::Pbm(_))
+        ));
+    }
+
+    #[test]
+    fn test_pbm_round_trip_with_lit_background() {
+        let data = load_data("resources/test_data_flipping.txt");
+        let once = data.image.process_with_algorithm(&data.enhance_algorithm);
+
+        for format in [PbmFormat::Plain, PbmFormat::Raw] {
+            let output = write(&once, once.bounds(), ImageFormat::Pbm(format));
+
+            let read = Image::read(&output).unwrap();
+            assert_eq!(read.background, 1);
+            assert_eq!(read, once);
+            assert_eq!(read.count_lit_pixels(), once.count_lit_pixels());
+        }
+        assert!(matches!(
+            Image::parse_pbm(b"P1\n# background 2\n1 1\n1"),
+            Err(ImageError::Pbm(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_pbm() {
+        let image = Image::parse_pbm(b"P1 # comment\n3 2 1 0\n1\n0 1 0").unwrap();
+
+        assert_eq!(image, Image::parse_text("#.#\n.#.").unwrap());
+        assert!(matches!(
+            Image::parse_pbm(b"P4\n9 2\n\xff"),
+            Err(ImageError::Pbm(_))
+        ));
+        assert!(matches!(
+            Image::parse_pbm(b"P1\n2 1\n12"),
+            Err(ImageError::Pbm(_))
+        ));
+        assert!(matches!(
+            Image::read(b"#.x"),
+            Err(ImageError::Text(ParseGridError::InvalidCharacter { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_crop() {
+        let mut image = Image::parse_text(TEXT).unwrap();
+        image.background = 1;
+
+        let cropped = image.crop(((-1, 3), (1, 5)));
+
+        assert_eq!(cropped.origin, (-1, 3));
+        assert_eq!(cropped.pixels.to_string(), "111\n101\n001");
+        assert_eq!(cropped.bounds(), ((-1, 3), (1, 5)));
+        let output = write(&image, ((2, 0), (2, 1)), ImageFormat::Text);
+        assert_eq!(String::from_utf8(output).unwrap(), "##\n");
+        assert!(image.crop(((1, 1), (0, 0))).pixels.is_empty());
+    }
+}
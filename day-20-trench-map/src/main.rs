@@ -1,152 +1,175 @@
 use std::{
-    collections::HashSet,
-    fs::File,
-    io::{BufRead, BufReader},
+    env,
+    error::Error,
+    fmt,
+    fs::{self, File},
+    io::{BufWriter, Write},
 };
 
+use common::{
+    automaton::{Automaton, LookupTable, PlaneCoordinates},
+    grid::{parse_coordinates, Coordinates, Grid},
+    visualisation::{Render, Visualiser, VisualiserOptions},
+};
+
+mod bit_image;
+mod image_io;
+
+use bit_image::BitImage;
+use image_io::{ImageFormat, PbmFormat};
+
+// The enhancement algorithm, a blank line and the image.
+fn parse_data(input: &str) -> Result<ImageWithEnhanceAlgorithm, Box<dyn Error>> {
+    let mut lines = input.lines();
+    let algorithm_data = lines
+        .by_ref()
+        .take_while(|line| !line.is_empty())
+        .collect::<String>();
+    let image_data = lines.collect::<Vec<_>>().join("\n");
+
+    Ok(ImageWithEnhanceAlgorithm {
+        image: Image::parse_text(&image_data)?,
+        enhance_algorithm: EnhanceAlgorithm {
+            lookup_table: algorithm_data.parse()?,
+        },
+    })
+}
+
 fn load_data(file_name: &str) -> ImageWithEnhanceAlgorithm {
-    let file = File::open(file_name).expect(&format!("Can't read file {}", file_name));
-    let file = BufReader::new(file);
-
-    fn convert_sign_into_num(c: char) -> u8 {
-        match c {
-            '.' => 0,
-            '#' => 1,
-            _ => panic!(),
-        }
-    }
-
-    let mut lines = file.lines();
-    let algorithm_data = lines.next().unwrap().unwrap();
-    let enhance_algorithm = EnhanceAlgorithm {
-        algorithm: algorithm_data
-            .chars()
-            .into_iter()
-            .map(convert_sign_into_num)
-            .collect(),
-    };
-
-    let consumed_empty_line = lines.next();
-    if !consumed_empty_line.unwrap().unwrap().is_empty() {
-        panic!()
-    }
-
-    let mut min_x = isize::MAX;
-    let mut max_x = isize::MIN;
-    let mut min_y = isize::MAX;
-    let mut max_y = isize::MIN;
-
-    let mut pixels = HashSet::new();
-    for (x, line) in lines.enumerate() {
-        for (y, value) in line.unwrap().chars().enumerate() {
-            if convert_sign_into_num(value) == 1 {
-                let x = x as isize;
-                let y = y as isize;
-                pixels.insert((x, y));
-                min_x = min_x.min(x);
-                max_x = max_x.max(x);
-                min_y = min_y.min(y);
-                max_y = max_y.max(y);
-            }
-        }
-    }
+    let input =
+        fs::read_to_string(file_name).unwrap_or_else(|_| panic!("Can't read file {}", file_name));
+    parse_data(&input).unwrap_or_else(|error| panic!("{}: {}", file_name, error))
+}
 
-    let image = Image {
-        pixels,
-        min_x,
-        max_x,
-        min_y,
-        max_y,
-        count_lit: true,
-    };
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum LitPixels {
+    Finite(usize),
+    // The background is lit.
+    Infinite,
+}
 
-    ImageWithEnhanceAlgorithm {
-        image,
-        enhance_algorithm,
+impl fmt::Display for LitPixels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LitPixels::Finite(count) => write!(f, "{}", count),
+            LitPixels::Infinite => write!(f, "infinitely many"),
+        }
     }
 }
 
+// Inclusive bounds of a rectangle on the image's plane.
+type Window = (PlaneCoordinates, PlaneCoordinates);
+
+// Finite part of an infinite image, every pixel outside of it is `background`.
+// The image lies on a plane, the original input starting at `(0, 0)`.
 #[derive(Debug, Eq, PartialEq, Clone)]
 struct Image {
-    pixels: HashSet<(isize, isize)>,
-    min_x: isize,
-    max_x: isize,
-    min_y: isize,
-    max_y: isize,
-    count_lit: bool,
+    pixels: Grid<u8>,
+    background: u8,
+    // Plane coordinates of `pixels[(0, 0)]`.
+    origin: PlaneCoordinates,
 }
 
 impl Image {
     pub fn process_with_algorithm(&self, algorithm: &EnhanceAlgorithm) -> Self {
-        let mut result_image = Image {
-            pixels: HashSet::new(),
-            min_x: isize::MAX,
-            max_x: isize::MIN,
-            min_y: isize::MAX,
-            max_y: isize::MIN,
-            count_lit: if algorithm.algorithm[0] == 1 {
-                !self.count_lit
-            } else {
-                true
-            },
-        };
-        for x in (self.min_x - 1)..=(self.max_x + 1) {
-            for y in (self.min_y - 1)..=(self.max_y + 1) {
-                let value_from_subimage = self.get_value_from_subimage(x, y);
-                if (result_image.count_lit && algorithm.algorithm[value_from_subimage] == 1)
-                    || (!result_image.count_lit && algorithm.algorithm[value_from_subimage] == 0)
-                {
-                    result_image.pixels.insert((x, y));
-                    result_image.min_x = result_image.min_x.min(x);
-                    result_image.max_x = result_image.max_x.max(x);
-                    result_image.min_y = result_image.min_y.min(y);
-                    result_image.max_y = result_image.max_y.max(y);
-                }
-            }
-        }
-        result_image
+        self.process_with_algorithm_n_times(algorithm, 1)
     }
 
-    pub fn count_lit_pixels(&self) -> Option<usize> {
-        if self.count_lit {
-            Some(self.pixels.len())
+    pub fn count_lit_pixels(&self) -> LitPixels {
+        if self.background == 0 {
+            LitPixels::Finite(self.pixels.iter().filter(|pixel| **pixel == 1).count())
         } else {
-            None
+            LitPixels::Infinite
         }
     }
 
+    pub fn count_lit_pixels_in(&self, ((x_min, y_min), (x_max, y_max)): Window) -> usize {
+        let span = |min: isize, max: isize| (max - min + 1).max(0) as usize;
+        let area = span(x_min, x_max) * span(y_min, y_max);
+
+        let (x_origin, y_origin) = self.origin;
+        let (inside, lit_inside) = self
+            .pixels
+            .cells()
+            .filter(|((x, y), _)| {
+                let (x, y) = (*x as isize + x_origin, *y as isize + y_origin);
+                (x_min..=x_max).contains(&x) && (y_min..=y_max).contains(&y)
+            })
+            .fold((0, 0), |(inside, lit), (_, pixel)| {
+                (inside + 1, lit + *pixel as usize)
+            });
+
+        lit_inside + (area - inside) * self.background as usize
+    }
+
+    // Enhancing is a cellular automaton with the algorithm as its lookup table,
+    // the image growing by `n` pixels on every side.
     pub fn process_with_algorithm_n_times(&self, algorithm: &EnhanceAlgorithm, n: usize) -> Self {
-        let mut image = self.clone();
-        for _ in 0..n {
-            image = image.process_with_algorithm(algorithm);
+        let mut automaton =
+            Automaton::new(self.pixels.clone(), self.background).with_origin(self.origin);
+        automaton.run(&algorithm.lookup_table, n);
+        let origin = automaton.origin();
+        let (pixels, background) = automaton.into_parts();
+        Image {
+            pixels,
+            background,
+            origin,
         }
+    }
 
-        image
+    fn pixel(&self, (x, y): PlaneCoordinates) -> u8 {
+        let (x, y) = (x - self.origin.0, y - self.origin.1);
+        if x < 0 || y < 0 {
+            return self.background;
+        }
+        self.pixels
+            .get((x as usize, y as usize))
+            .copied()
+            .unwrap_or(self.background)
     }
+}
 
-    fn get_value_from_subimage(&self, x: isize, y: isize) -> usize {
-        let mut result = 0usize;
+// The image drawn on a fixed canvas, so frames keep their size while the image
+// grows by one pixel on every side with each step.
+struct ImageFrame<'a> {
+    image: &'a Image,
+    // Plane coordinates of the canvas' top left pixel.
+    origin: PlaneCoordinates,
+    size: (usize, usize),
+}
 
-        for current_x in (x - 1)..=(x + 1) {
-            for current_y in (y - 1)..=(y + 1) {
-                result = (result << 1)
-                    | if (self.count_lit && self.pixels.contains(&(current_x, current_y)))
-                        || (!self.count_lit && !self.pixels.contains(&(current_x, current_y)))
-                    {
-                        1
-                    } else {
-                        0
-                    };
-            }
+impl ImageFrame<'_> {
+    fn pixel(&self, (x, y): Coordinates) -> u8 {
+        self.image
+            .pixel((self.origin.0 + x as isize, self.origin.1 + y as isize))
+    }
+}
+
+impl Render for ImageFrame<'_> {
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    fn glyph(&self, coordinates: Coordinates) -> char {
+        if self.pixel(coordinates) == 1 {
+            '#'
+        } else {
+            '.'
         }
+    }
 
-        result
+    fn colour(&self, coordinates: Coordinates) -> [u8; 3] {
+        if self.pixel(coordinates) == 1 {
+            [240, 240, 240]
+        } else {
+            [40, 40, 60]
+        }
     }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 struct EnhanceAlgorithm {
-    algorithm: Vec<u8>,
+    lookup_table: LookupTable,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -160,10 +183,9 @@ fn part_1_result(file_name: &str) {
         image,
         enhance_algorithm: algorithm,
     } = load_data(file_name);
-    let lit_pixels = image
-        .process_with_algorithm_n_times(&algorithm, 2)
-        .count_lit_pixels()
-        .unwrap();
+    let lit_pixels = BitImage::from(&image)
+        .enhance_n_times(&algorithm.lookup_table, 2)
+        .count_lit_pixels();
 
     println!("Part 1. Result: {}", lit_pixels);
 }
@@ -173,24 +195,298 @@ fn part_2_result(file_name: &str) {
         image,
         enhance_algorithm: algorithm,
     } = load_data(file_name);
-    let lit_pixels = image
-        .process_with_algorithm_n_times(&algorithm, 50)
-        .count_lit_pixels()
-        .unwrap();
+    let lit_pixels = BitImage::from(&image)
+        .enhance_n_times(&algorithm.lookup_table, 50)
+        .count_lit_pixels();
 
     println!("Part 2. Result: {}", lit_pixels);
 }
 
+#[derive(Default)]
+struct ImageOptions {
+    image: Option<String>,
+    output: Option<String>,
+    format: Option<ImageFormat>,
+    viewport: Option<Window>,
+    window: Option<Window>,
+    visualiser: VisualiserOptions,
+    steps: usize,
+}
+
+impl ImageOptions {
+    fn from_args<I: Iterator<Item = String>>(mut args: I) -> Self {
+        let mut options = ImageOptions {
+            steps: 50,
+            ..Default::default()
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .unwrap_or_else(|| panic!("Missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "--image" => options.image = Some(value()),
+                "--output" => options.output = Some(value()),
+                "--format" => options.format = Some(value().parse().unwrap()),
+                "--viewport" => options.viewport = Some(parse_window(&value())),
+                "--window" => options.window = Some(parse_window(&value())),
+                "--steps" => options.steps = value().parse().unwrap(),
+                _ => {
+                    if !options.visualiser.parse_arg(&arg, &mut args) {
+                        panic!("Unknown argument {}", arg)
+                    }
+                }
+            }
+        }
+        options
+    }
+
+    fn any(&self) -> bool {
+        self.output.is_some() || self.window.is_some() || self.visualiser.any()
+    }
+}
+
+// The puzzle's algorithm, applied to the image from `--image` if given.
+fn load_data_with_options(file_name: &str, options: &ImageOptions) -> ImageWithEnhanceAlgorithm {
+    let mut data = load_data(file_name);
+    if let Some(image_file) = &options.image {
+        let input =
+            fs::read(image_file).unwrap_or_else(|_| panic!("Can't read file {}", image_file));
+        data.image =
+            Image::read(&input).unwrap_or_else(|error| panic!("{}: {}", image_file, error));
+    }
+    data
+}
+
+// `x,y:x,y`, the corners of the window.
+fn parse_window(window: &str) -> Window {
+    let corner = |corner| parse_coordinates(corner).unwrap_or_else(|error| panic!("{}", error));
+    let (min, max) = window
+        .split_once(':')
+        .unwrap_or_else(|| panic!("Expected <x>,<y>:<x>,<y>, got {}", window));
+    (corner(min), corner(max))
+}
+
+fn image_result(file_name: &str, options: &ImageOptions) {
+    let ImageWithEnhanceAlgorithm {
+        image,
+        enhance_algorithm: algorithm,
+    } = load_data_with_options(file_name, options);
+    let image = image.process_with_algorithm_n_times(&algorithm, options.steps);
+
+    if let Some(window) = options.window {
+        println!(
+            "Lit pixels after {} steps: {}, between {:?} and {:?}: {}",
+            options.steps,
+            image.count_lit_pixels(),
+            window.0,
+            window.1,
+            image.count_lit_pixels_in(window)
+        );
+    }
+
+    if let Some(output) = &options.output {
+        let format = options.format.unwrap_or(if output.ends_with(".pbm") {
+            ImageFormat::Pbm(PbmFormat::Raw)
+        } else {
+            ImageFormat::Text
+        });
+        // Text has no room for the background, a lit one would come back dark.
+        if format == ImageFormat::Text && image.background == 1 && options.viewport.is_none() {
+            panic!(
+                "The background is lit after {} steps, which text can't record. \
+                 Export to PBM or pick the pixels with --viewport",
+                options.steps
+            );
+        }
+        let viewport = options.viewport.unwrap_or_else(|| image.bounds());
+        let file = File::create(output).unwrap_or_else(|_| panic!("Can't create file {}", output));
+        let mut writer = BufWriter::new(file);
+        image.write(viewport, format, &mut writer).unwrap();
+        writer.flush().unwrap();
+        println!(
+            "Exported pixels between {:?} and {:?} after {} steps to {}",
+            viewport.0, viewport.1, options.steps, output
+        );
+    }
+}
+
+fn animation_result(file_name: &str, options: &ImageOptions) {
+    let ImageWithEnhanceAlgorithm {
+        mut image,
+        enhance_algorithm: algorithm,
+    } = load_data_with_options(file_name, options);
+    let mut visualiser =
+        Visualiser::from_options(&options.visualiser).unwrap_or_else(|error| panic!("{}", error));
+
+    let (x_size, y_size) = image.pixels.size();
+    let size = (x_size + 2 * options.steps, y_size + 2 * options.steps);
+    let origin = (-(options.steps as isize), -(options.steps as isize));
+    for step in 0..=options.steps {
+        if step > 0 {
+            image = image.process_with_algorithm(&algorithm);
+        }
+        let frame = ImageFrame {
+            image: &image,
+            origin,
+            size,
+        };
+        let caption = format!("Step {}: {} lit pixels", step, image.count_lit_pixels());
+        visualiser.show(&frame, &caption).unwrap();
+    }
+
+    if let Some(summary) = visualiser.finish().unwrap() {
+        println!("{}", summary);
+    }
+}
+
 fn main() {
     const DATA_FILENAME: &str = "./resources/data.txt";
+    let image_options = ImageOptions::from_args(env::args().skip(1));
+    if image_options.any() {
+        if image_options.window.is_some() || image_options.output.is_some() {
+            image_result(DATA_FILENAME, &image_options);
+        }
+        if image_options.visualiser.any() {
+            animation_result(DATA_FILENAME, &image_options);
+        }
+        return;
+    }
     part_1_result(DATA_FILENAME);
     part_2_result(DATA_FILENAME);
 }
 
+// Reference implementation on sets of pixels. While `count_lit` holds the set
+// keeps the lit pixels, otherwise the background is lit and it keeps the dark
+// ones.
+#[cfg(test)]
+mod solver {
+    use std::collections::HashSet;
+
+    use crate::{EnhanceAlgorithm, Image};
+
+    #[derive(Debug, Eq, PartialEq, Clone)]
+    pub struct SetImage {
+        pixels: HashSet<(isize, isize)>,
+        min_x: isize,
+        max_x: isize,
+        min_y: isize,
+        max_y: isize,
+        count_lit: bool,
+    }
+
+    impl SetImage {
+        pub fn from_image(image: &Image) -> Self {
+            let mut result = SetImage {
+                pixels: HashSet::new(),
+                min_x: isize::MAX,
+                max_x: isize::MIN,
+                min_y: isize::MAX,
+                max_y: isize::MIN,
+                count_lit: image.background == 0,
+            };
+            let (x_origin, y_origin) = image.origin;
+            for ((x, y), pixel) in image.pixels.cells() {
+                if *pixel != image.background {
+                    result.insert((x as isize + x_origin, y as isize + y_origin));
+                }
+            }
+            result
+        }
+
+        fn insert(&mut self, (x, y): (isize, isize)) {
+            self.pixels.insert((x, y));
+            self.min_x = self.min_x.min(x);
+            self.max_x = self.max_x.max(x);
+            self.min_y = self.min_y.min(y);
+            self.max_y = self.max_y.max(y);
+        }
+
+        pub fn process_with_algorithm(&self, algorithm: &EnhanceAlgorithm) -> Self {
+            let algorithm = &algorithm.lookup_table;
+            let mut result_image = SetImage {
+                pixels: HashSet::new(),
+                min_x: isize::MAX,
+                max_x: isize::MIN,
+                min_y: isize::MAX,
+                max_y: isize::MIN,
+                count_lit: if algorithm.get(0) == 1 {
+                    !self.count_lit
+                } else {
+                    true
+                },
+            };
+            for x in (self.min_x - 1)..=(self.max_x + 1) {
+                for y in (self.min_y - 1)..=(self.max_y + 1) {
+                    let value_from_subimage = self.get_value_from_subimage(x, y);
+                    if (result_image.count_lit && algorithm.get(value_from_subimage) == 1)
+                        || (!result_image.count_lit && algorithm.get(value_from_subimage) == 0)
+                    {
+                        result_image.insert((x, y));
+                    }
+                }
+            }
+            result_image
+        }
+
+        pub fn count_lit_pixels(&self) -> Option<usize> {
+            if self.count_lit {
+                Some(self.pixels.len())
+            } else {
+                None
+            }
+        }
+
+        pub fn process_with_algorithm_n_times(
+            &self,
+            algorithm: &EnhanceAlgorithm,
+            n: usize,
+        ) -> Self {
+            let mut image = self.clone();
+            for _ in 0..n {
+                image = image.process_with_algorithm(algorithm);
+            }
+
+            image
+        }
+
+        fn get_value_from_subimage(&self, x: isize, y: isize) -> usize {
+            let mut result = 0usize;
+
+            for current_x in (x - 1)..=(x + 1) {
+                for current_y in (y - 1)..=(y + 1) {
+                    result = (result << 1)
+                        | if (self.count_lit && self.pixels.contains(&(current_x, current_y)))
+                            || (!self.count_lit && !self.pixels.contains(&(current_x, current_y)))
+                        {
+                            1
+                        } else {
+                            0
+                        };
+                }
+            }
+
+            result
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_crlf_data() {
+        let input = fs::read_to_string("resources/test_data.txt").unwrap();
+
+        let crlf = parse_data(&input.replace('\n', "\r\n")).unwrap();
+
+        assert_eq!(crlf.image, parse_data(&input).unwrap().image);
+        assert_eq!(crlf.image.pixels.size(), (5, 5));
+        assert_eq!(crlf.enhance_algorithm.lookup_table.get(34), 1);
+        assert!(parse_data("#.#\n\n#.").is_err());
+    }
+
     #[test]
     fn test_part_1() {
         let ImageWithEnhanceAlgorithm {
@@ -199,8 +495,112 @@ mod tests {
         } = load_data("resources/test_data.txt");
         let result = image
             .process_with_algorithm_n_times(&algorithm, 2)
-            .count_lit_pixels()
-            .unwrap();
-        assert_eq!(result, 35);
+            .count_lit_pixels();
+        assert_eq!(result, LitPixels::Finite(35));
+    }
+
+    #[test]
+    fn test_solver() {
+        let ImageWithEnhanceAlgorithm {
+            image,
+            enhance_algorithm: algorithm,
+        } = load_data("resources/test_data.txt");
+        let image = solver::SetImage::from_image(&image);
+
+        let twice = image.process_with_algorithm_n_times(&algorithm, 2);
+        let fifty = image.process_with_algorithm_n_times(&algorithm, 50);
+
+        assert_eq!(twice.count_lit_pixels(), Some(35));
+        assert_eq!(fifty.count_lit_pixels(), Some(3351));
+    }
+
+    #[test]
+    fn test_image_frame() {
+        let ImageWithEnhanceAlgorithm {
+            image,
+            enhance_algorithm: algorithm,
+        } = load_data("resources/test_data.txt");
+        let processed = image.process_with_algorithm(&algorithm);
+
+        let frame = ImageFrame {
+            image: &image,
+            origin: processed.origin,
+            size: processed.pixels.size(),
+        };
+
+        assert_eq!(Render::size(&frame), (7, 7));
+        assert_eq!(frame.glyph((0, 0)), '.');
+        assert_eq!(frame.glyph((1, 1)), '#');
+        assert_eq!(frame.colour((1, 1)), [240, 240, 240]);
+    }
+
+    #[test]
+    fn test_process_matches_single_steps() {
+        let ImageWithEnhanceAlgorithm {
+            image,
+            enhance_algorithm: algorithm,
+        } = load_data("resources/test_data.txt");
+
+        let stepped = image
+            .process_with_algorithm(&algorithm)
+            .process_with_algorithm(&algorithm);
+
+        // The image after two steps from the puzzle description.
+        let expected = Image {
+            origin: (-2, -2),
+            ..Image::parse_text(
+                ".......#.\n\
+                 .#..#.#..\n\
+                 #.#...###\n\
+                 #...##.#.\n\
+                 #.....#.#\n\
+                 .#.#####.\n\
+                 ..#.#####\n\
+                 ...##.##.\n\
+                 ....###..",
+            )
+            .unwrap()
+        };
+        assert_eq!(stepped, expected);
+        assert_eq!(
+            image.process_with_algorithm_n_times(&algorithm, 2),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_infinite_background() {
+        let ImageWithEnhanceAlgorithm {
+            image,
+            enhance_algorithm: algorithm,
+        } = load_data("resources/test_data_flipping.txt");
+
+        let once = image.process_with_algorithm(&algorithm);
+        let twice = once.process_with_algorithm(&algorithm);
+
+        assert_eq!(once.background, 1);
+        assert_eq!(once.count_lit_pixels(), LitPixels::Infinite);
+        assert_eq!(once.count_lit_pixels().to_string(), "infinitely many");
+        assert_eq!(twice.count_lit_pixels(), LitPixels::Finite(24));
+        assert_eq!(once.origin, (-1, -1));
+    }
+
+    #[test]
+    fn test_count_lit_pixels_in_window() {
+        let ImageWithEnhanceAlgorithm {
+            image,
+            enhance_algorithm: algorithm,
+        } = load_data("resources/test_data_flipping.txt");
+        let once = image.process_with_algorithm(&algorithm);
+        let twice = once.process_with_algorithm(&algorithm);
+
+        // The window covers the whole image plus a border of 10 pixels.
+        let (x_size, y_size) = once.pixels.size();
+        let window = ((-11, -11), (x_size as isize + 8, y_size as isize + 8));
+        let outside = (x_size + 20) * (y_size + 20) - x_size * y_size;
+        assert_eq!(once.count_lit_pixels_in(window), 30 + outside);
+        assert_eq!(twice.count_lit_pixels_in(((-100, -100), (200, 200))), 24);
+        assert_eq!(twice.count_lit_pixels_in(((5, 5), (4, 4))), 0);
+        assert_eq!(once.count_lit_pixels_in(((-50, -50), (-41, -41))), 100);
     }
 }
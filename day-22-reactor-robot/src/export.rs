@@ -0,0 +1,419 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Write},
+};
+
+use crate::cuboid::Cuboid;
+
+// Vertex indices of the six box faces, counter-clockwise when seen from outside.
+const BOX_FACES: [[usize; 4]; 6] = [
+    [0, 3, 2, 1],
+    [4, 5, 6, 7],
+    [0, 1, 5, 4],
+    [3, 7, 6, 2],
+    [0, 4, 7, 3],
+    [1, 2, 6, 5],
+];
+
+const BOX_FACE_NORMALS: [[isize; 3]; 6] = [
+    [0, 0, -1],
+    [0, 0, 1],
+    [0, -1, 0],
+    [0, 1, 0],
+    [-1, 0, 0],
+    [1, 0, 0],
+];
+
+fn sorted_cuboids(cuboids: &HashSet<Cuboid>) -> Vec<&Cuboid> {
+    let mut sorted = cuboids.iter().collect::<Vec<_>>();
+    sorted.sort();
+    sorted
+}
+
+fn box_vertices(cuboid: &Cuboid) -> [[isize; 3]; 8] {
+    // A cuboid x=a..b covers the unit cubes a..=b, so its box ends at b + 1.
+    let [(x0, x1), (y0, y1), (z0, z1)] = cuboid.ranges.map(|(min, max)| (min, max + 1));
+    [
+        [x0, y0, z0],
+        [x1, y0, z0],
+        [x1, y1, z0],
+        [x0, y1, z0],
+        [x0, y0, z1],
+        [x1, y0, z1],
+        [x1, y1, z1],
+        [x0, y1, z1],
+    ]
+}
+
+pub fn merge_cuboids(cuboids: &HashSet<Cuboid>) -> HashSet<Cuboid> {
+    fn merge_along_axis(cuboids: HashSet<Cuboid>, axis: usize) -> (HashSet<Cuboid>, bool) {
+        // Cuboids can only be glued along an axis when all other ranges are equal.
+        let mut groups: HashMap<Cuboid, Vec<Cuboid>> = HashMap::new();
+        for cuboid in cuboids {
+            let mut key = cuboid;
+            key.ranges[axis] = (0, 0);
+            groups.entry(key).or_default().push(cuboid);
+        }
+
+        let mut merged_any = false;
+        let mut result = HashSet::new();
+        for (_, mut group) in groups {
+            group.sort_by_key(|cuboid| cuboid.ranges[axis]);
+            let mut group = group.into_iter();
+            let mut current = group.next().unwrap();
+            for next in group {
+                if current.ranges[axis].1 + 1 == next.ranges[axis].0 {
+                    current.ranges[axis].1 = next.ranges[axis].1;
+                    merged_any = true;
+                } else {
+                    result.insert(current);
+                    current = next;
+                }
+            }
+            result.insert(current);
+        }
+
+        (result, merged_any)
+    }
+
+    let mut merged = cuboids.clone();
+    loop {
+        let mut merged_any = false;
+        for axis in 0..3 {
+            let (result, merged_along_axis) = merge_along_axis(merged, axis);
+            merged = result;
+            merged_any |= merged_along_axis;
+        }
+        if !merged_any {
+            return merged;
+        }
+    }
+}
+
+pub fn write_obj<W: Write>(cuboids: &HashSet<Cuboid>, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "# reactor cuboids: {}", cuboids.len())?;
+    for (index, cuboid) in sorted_cuboids(cuboids).into_iter().enumerate() {
+        writeln!(writer, "o cuboid_{}", index)?;
+        for [x, y, z] in box_vertices(cuboid) {
+            writeln!(writer, "v {} {} {}", x, y, z)?;
+        }
+        let offset = index * 8 + 1;
+        for face in BOX_FACES {
+            writeln!(
+                writer,
+                "f {} {} {} {}",
+                face[0] + offset,
+                face[1] + offset,
+                face[2] + offset,
+                face[3] + offset
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn write_stl<W: Write>(cuboids: &HashSet<Cuboid>, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "solid reactor")?;
+    for cuboid in sorted_cuboids(cuboids) {
+        let vertices = box_vertices(cuboid);
+        for (face, [nx, ny, nz]) in BOX_FACES.iter().zip(BOX_FACE_NORMALS) {
+            for triangle in [[face[0], face[1], face[2]], [face[0], face[2], face[3]]] {
+                writeln!(writer, "  facet normal {} {} {}", nx, ny, nz)?;
+                writeln!(writer, "    outer loop")?;
+                for vertex in triangle {
+                    let [x, y, z] = vertices[vertex];
+                    writeln!(writer, "      vertex {} {} {}", x, y, z)?;
+                }
+                writeln!(writer, "    endloop")?;
+                writeln!(writer, "  endfacet")?;
+            }
+        }
+    }
+    writeln!(writer, "endsolid reactor")
+}
+
+// Largest raster a slice is rendered to, 2000x2000 pixels. Slices of bigger
+// areas get a coarser scale.
+pub const MAX_SLICE_PIXELS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VoxelSlice {
+    pub z: isize,
+    pub x: (isize, isize),
+    pub y: (isize, isize),
+    pub scale: usize,
+}
+
+impl VoxelSlice {
+    // `scale` is raised as far as needed for the raster to fit in
+    // `MAX_SLICE_PIXELS`.
+    pub fn new(z: isize, x: (isize, isize), y: (isize, isize), scale: usize) -> Self {
+        let mut scale = scale.max(1);
+        let pixels = |scale| Self::pixels_for(x, scale) * Self::pixels_for(y, scale);
+        if pixels(scale) > MAX_SLICE_PIXELS {
+            // Each axis shrinks by about `scale`, so the pixel count by its square.
+            let ratio = pixels(1) as f64 / MAX_SLICE_PIXELS as f64;
+            scale = scale.max(ratio.sqrt() as usize);
+            while pixels(scale) > MAX_SLICE_PIXELS {
+                scale += 1;
+            }
+        }
+        Self { z, x, y, scale }
+    }
+
+    pub fn bounding(cuboids: &HashSet<Cuboid>, z: isize, scale: usize) -> Option<Self> {
+        let in_slice = cuboids
+            .iter()
+            .filter(|cuboid| cuboid.ranges[2].0 <= z && z <= cuboid.ranges[2].1);
+        let x = in_slice.clone().map(|cuboid| cuboid.ranges[0]);
+        let y = in_slice.map(|cuboid| cuboid.ranges[1]);
+        let x = (x.clone().map(|x| x.0).min()?, x.map(|x| x.1).max()?);
+        let y = (y.clone().map(|y| y.0).min()?, y.map(|y| y.1).max()?);
+        Some(Self::new(z, x, y, scale))
+    }
+
+    pub fn width(&self) -> usize {
+        Self::pixels_for(self.x, self.scale)
+    }
+
+    pub fn height(&self) -> usize {
+        Self::pixels_for(self.y, self.scale)
+    }
+
+    fn pixels_for(range: (isize, isize), scale: usize) -> usize {
+        ((range.1 - range.0 + 1).max(0) as usize).div_ceil(scale)
+    }
+
+    // A pixel belongs to every cuboid covering any cell of the area it
+    // covers, so cuboids thinner than a pixel don't vanish when scaled down.
+    fn pixel_range(
+        range: (isize, isize),
+        cuboid_range: (isize, isize),
+        scale: usize,
+    ) -> (isize, isize) {
+        let scale = scale as isize;
+        let first = (cuboid_range.0 - range.0).div_euclid(scale);
+        let last = (cuboid_range.1 - range.0).div_euclid(scale);
+        let pixels = Self::pixels_for(range, scale as usize) as isize;
+        (first.max(0), last.min(pixels - 1))
+    }
+
+    // 0 for pixels no cuboid covers, the cuboid's position in sorted order plus
+    // one otherwise.
+    fn label_pixels(&self, cuboids: &HashSet<Cuboid>) -> Vec<u32> {
+        let width = self.width();
+        let mut labels = vec![0; width * self.height()];
+        for (index, cuboid) in sorted_cuboids(cuboids).into_iter().enumerate() {
+            let [x, y, z] = cuboid.ranges;
+            if z.0 > self.z || self.z > z.1 {
+                continue;
+            }
+            let (column_min, column_max) = Self::pixel_range(self.x, x, self.scale);
+            let (row_min, row_max) = Self::pixel_range(self.y, y, self.scale);
+            for row in row_min..=row_max {
+                for column in column_min..=column_max {
+                    labels[row as usize * width + column as usize] = index as u32 + 1;
+                }
+            }
+        }
+        labels
+    }
+
+    pub fn write_pgm<W: Write>(&self, cuboids: &HashSet<Cuboid>, writer: &mut W) -> io::Result<()> {
+        let labels = self.label_pixels(cuboids);
+        writeln!(writer, "P2")?;
+        writeln!(
+            writer,
+            "# z={} x={}..{} y={}..{}",
+            self.z, self.x.0, self.x.1, self.y.0, self.y.1
+        )?;
+        writeln!(writer, "{} {}", self.width(), self.height())?;
+        writeln!(writer, "255")?;
+        for row in labels.chunks(self.width().max(1)) {
+            let row = row
+                .iter()
+                .map(|label| if *label > 0 { "255" } else { "0" })
+                .collect::<Vec<_>>();
+            writeln!(writer, "{}", row.join(" "))?;
+        }
+        Ok(())
+    }
+
+    pub fn write_ppm<W: Write>(&self, cuboids: &HashSet<Cuboid>, writer: &mut W) -> io::Result<()> {
+        // Neighbouring cuboids get different colours so the decomposition stays visible.
+        let colour = |label: u32| {
+            let hash = label.wrapping_mul(2_654_435_761);
+            [
+                64 + (hash >> 24) % 192,
+                64 + (hash >> 16) % 192,
+                64 + (hash >> 8) % 192,
+            ]
+        };
+
+        let labels = self.label_pixels(cuboids);
+        writeln!(writer, "P3")?;
+        writeln!(
+            writer,
+            "# z={} x={}..{} y={}..{}",
+            self.z, self.x.0, self.x.1, self.y.0, self.y.1
+        )?;
+        writeln!(writer, "{} {}", self.width(), self.height())?;
+        writeln!(writer, "255")?;
+        for row in labels.chunks(self.width().max(1)) {
+            let row = row
+                .iter()
+                .map(|label| {
+                    let [r, g, b] = if *label > 0 {
+                        colour(*label)
+                    } else {
+                        [0, 0, 0]
+                    };
+                    format!("{} {} {}", r, g, b)
+                })
+                .collect::<Vec<_>>();
+            writeln!(writer, "{}", row.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cuboids(cuboids: Vec<Cuboid>) -> HashSet<Cuboid> {
+        cuboids.into_iter().collect()
+    }
+
+    #[test]
+    fn test_merge_cuboids() {
+        let split = cuboids(vec![
+            Cuboid::new((0, 1), (0, 1), (0, 1)),
+            Cuboid::new((2, 3), (0, 1), (0, 1)),
+            Cuboid::new((0, 3), (2, 2), (0, 1)),
+            Cuboid::new((10, 10), (0, 0), (0, 0)),
+        ]);
+
+        let merged = merge_cuboids(&split);
+
+        assert_eq!(
+            merged,
+            cuboids(vec![
+                Cuboid::new((0, 3), (0, 2), (0, 1)),
+                Cuboid::new((10, 10), (0, 0), (0, 0)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_write_obj() {
+        let mut output = Vec::new();
+        write_obj(
+            &cuboids(vec![Cuboid::new((0, 1), (0, 0), (0, 0))]),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        let vertices = output.lines().filter(|line| line.starts_with("v ")).count();
+        let faces = output.lines().filter(|line| line.starts_with("f ")).count();
+        assert_eq!((vertices, faces), (8, 6));
+        assert!(output.contains("v 2 1 1\n"));
+        assert!(output.contains("f 1 4 3 2\n"));
+    }
+
+    #[test]
+    fn test_write_stl() {
+        let mut output = Vec::new();
+        let data = cuboids(vec![
+            Cuboid::new((0, 0), (0, 0), (0, 0)),
+            Cuboid::new((5, 6), (5, 6), (5, 6)),
+        ]);
+        write_stl(&data, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.starts_with("solid reactor\n"));
+        assert!(output.ends_with("endsolid reactor\n"));
+        assert_eq!(output.matches("facet normal").count(), 24);
+    }
+
+    #[test]
+    fn test_write_pgm() {
+        let data = cuboids(vec![
+            Cuboid::new((0, 1), (0, 0), (0, 3)),
+            Cuboid::new((2, 2), (1, 1), (1, 1)),
+        ]);
+
+        let mut output = Vec::new();
+        let slice = VoxelSlice::bounding(&data, 0, 1).unwrap();
+        slice.write_pgm(&data, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "P2\n# z=0 x=0..1 y=0..0\n2 1\n255\n255 255\n"
+        );
+
+        let mut output = Vec::new();
+        let slice = VoxelSlice::new(1, (0, 3), (0, 1), 1);
+        slice.write_pgm(&data, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "P2\n# z=1 x=0..3 y=0..1\n4 2\n255\n255 255 0 0\n0 0 255 0\n"
+        );
+    }
+
+    #[test]
+    fn test_write_ppm_scaled() {
+        let data = cuboids(vec![Cuboid::new((0, 3), (0, 1), (0, 0))]);
+
+        let mut output = Vec::new();
+        let slice = VoxelSlice::new(0, (0, 7), (0, 1), 2);
+        slice.write_ppm(&data, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let mut lines = output.lines().skip(2);
+
+        assert_eq!(lines.next(), Some("4 1"));
+        let pixels = lines.nth(1).unwrap().split(' ').collect::<Vec<_>>();
+        assert_eq!(pixels.len(), 12);
+        assert_ne!(&pixels[0..3], ["0", "0", "0"]);
+        assert_eq!(&pixels[0..3], &pixels[3..6]);
+        assert_eq!(&pixels[6..12], ["0", "0", "0", "0", "0", "0"]);
+    }
+
+    #[test]
+    fn test_thin_cuboid_scaled() {
+        let data = cuboids(vec![
+            Cuboid::new((5, 5), (0, 7), (0, 0)),
+            Cuboid::new((-3, 20), (13, 13), (0, 0)),
+        ]);
+
+        let mut output = Vec::new();
+        let slice = VoxelSlice::new(0, (0, 15), (0, 15), 4);
+        slice.write_pgm(&data, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "P2\n# z=0 x=0..15 y=0..15\n4 4\n255\n\
+             0 255 0 0\n0 255 0 0\n0 0 0 0\n255 255 255 255\n"
+        );
+    }
+
+    #[test]
+    fn test_write_pgm_wide_range() {
+        let data = cuboids(vec![
+            Cuboid::new((-100_000, 99_999), (-100_000, -99_999), (-5, 5)),
+            Cuboid::new((0, 0), (0, 99_999), (0, 0)),
+        ]);
+
+        let slice = VoxelSlice::bounding(&data, 0, 1).unwrap();
+        let mut output = Vec::new();
+        slice.write_pgm(&data, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(slice.scale, 100);
+        assert_eq!((slice.width(), slice.height()), (2000, 2000));
+        assert!(slice.width() * slice.height() <= MAX_SLICE_PIXELS);
+        assert!(output.starts_with("P2\n# z=0 x=-100000..99999 y=-100000..99999\n2000 2000\n"));
+        assert_eq!(VoxelSlice::new(0, (0, 9), (0, 9), 3).scale, 3);
+    }
+}
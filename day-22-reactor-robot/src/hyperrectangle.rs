@@ -0,0 +1,165 @@
+use crate::{parse_reboot_step_error::ParseRebootStepError, reboot_step_parser::RebootStepParser};
+use std::{collections::HashSet, fmt, str::FromStr};
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct Hyperrectangle<const N: usize> {
+    pub ranges: [(isize, isize); N],
+}
+
+const AXIS_NAMES: [&str; 4] = ["x", "y", "z", "w"];
+
+pub fn axis_name(axis: usize) -> String {
+    AXIS_NAMES
+        .get(axis)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("d{}", axis))
+}
+
+pub fn axis_index(name: &str) -> Option<usize> {
+    AXIS_NAMES
+        .iter()
+        .position(|axis_name| *axis_name == name)
+        .or_else(|| {
+            name.strip_prefix('d')
+                .and_then(|index| index.parse().ok())
+                .filter(|index| *index >= AXIS_NAMES.len())
+        })
+}
+
+impl<const N: usize> Hyperrectangle<N> {
+    pub const fn from_ranges(ranges: [(isize, isize); N]) -> Self {
+        Self { ranges }
+    }
+
+    pub fn count_cubes_on(&self) -> usize {
+        self.ranges
+            .iter()
+            .map(|(min, max)| (max - min + 1).unsigned_abs())
+            .product()
+    }
+
+    pub fn contains(&self, other: &Self) -> bool {
+        self.ranges
+            .iter()
+            .zip(other.ranges.iter())
+            .all(|((min, max), (other_min, other_max))| other_min >= min && other_max <= max)
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.ranges
+            .iter()
+            .zip(other.ranges.iter())
+            .all(|((min, max), (other_min, other_max))| other_min <= max && min <= other_max)
+    }
+
+    pub fn split(&self, other: &Self) -> HashSet<Self> {
+        let cut = |(value_min, value_max): (isize, isize),
+                   (other_value_min, other_value_max): (isize, isize)| {
+            let mut cut_result = vec![value_min];
+            if other_value_min > value_min && other_value_min <= value_max {
+                cut_result.push(other_value_min - 1);
+                cut_result.push(other_value_min);
+            }
+            if other_value_max >= value_min && other_value_max < value_max {
+                cut_result.push(other_value_max);
+                cut_result.push(other_value_max + 1);
+            }
+            cut_result.push(value_max);
+            cut_result
+        };
+
+        let mut pieces = vec![*self];
+        for axis in 0..N {
+            let cuts = cut(self.ranges[axis], other.ranges[axis]);
+            pieces = pieces
+                .into_iter()
+                .flat_map(|piece| {
+                    cuts.chunks(2).map(move |range| {
+                        let mut piece = piece;
+                        piece.ranges[axis] = (range[0], range[1]);
+                        piece
+                    })
+                })
+                .collect();
+        }
+
+        pieces
+            .into_iter()
+            .filter(|piece| !other.overlaps(piece))
+            .collect()
+    }
+}
+
+impl<const N: usize> FromStr for Hyperrectangle<N> {
+    type Err = ParseRebootStepError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RebootStepParser::new(s).hyperrectangle()
+    }
+}
+
+impl<const N: usize> fmt::Display for Hyperrectangle<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (axis, (min, max)) in self.ranges.iter().enumerate() {
+            if axis > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}={}..{}", axis_name(axis), min, max)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_2d() {
+        let square = Hyperrectangle::from_ranges([(0, 2), (0, 2)]);
+        let hole = Hyperrectangle::from_ranges([(1, 1), (1, 1)]);
+
+        let pieces = square.split(&hole);
+
+        assert_eq!(pieces.len(), 8);
+        assert_eq!(
+            pieces
+                .iter()
+                .map(|piece| piece.count_cubes_on())
+                .sum::<usize>(),
+            8
+        );
+        assert!(pieces.iter().all(|piece| !piece.overlaps(&hole)));
+    }
+
+    #[test]
+    fn test_from_str_4d() {
+        let hyperrectangle = "x=-1..1,y=2..3,z=0..0,w=-5..-4"
+            .parse::<Hyperrectangle<4>>()
+            .unwrap();
+        assert_eq!(
+            hyperrectangle,
+            Hyperrectangle::from_ranges([(-1, 1), (2, 3), (0, 0), (-5, -4)])
+        );
+        assert_eq!(hyperrectangle.count_cubes_on(), 12);
+        assert!("x=-1..1,y=2..3,z=0..0"
+            .parse::<Hyperrectangle<4>>()
+            .is_err());
+        assert!("x=-1..1,y=2..3,z=0..0,w=1..1"
+            .parse::<Hyperrectangle<3>>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_extra_axes() {
+        let hyperrectangle = "d5=1..1,x=0..0,d4=2..3,w=0..0,z=0..0,y=0..0"
+            .parse::<Hyperrectangle<6>>()
+            .unwrap();
+        assert_eq!(hyperrectangle.ranges[4], (2, 3));
+        assert_eq!(
+            hyperrectangle.to_string(),
+            "x=0..0,y=0..0,z=0..0,w=0..0,d4=2..3,d5=1..1"
+        );
+        assert_eq!(axis_index("d3"), None);
+    }
+}
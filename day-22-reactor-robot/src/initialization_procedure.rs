@@ -1,23 +1,23 @@
 use std::collections::HashSet;
 
-use crate::{cuboid::Cuboid, cuboids_range::CuboidsRange, reboot_step::RebootStep};
+use crate::{hyperrectangle::Hyperrectangle, reboot_step::RebootStep};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
-pub struct InitializationProcedure {
-    pub steps: Vec<RebootStep>,
+pub struct InitializationProcedure<const N: usize> {
+    pub steps: Vec<RebootStep<N>>,
 }
 
-impl InitializationProcedure {
-    pub fn new(steps: Vec<RebootStep>) -> Self {
+impl<const N: usize> InitializationProcedure<N> {
+    pub fn new(steps: Vec<RebootStep<N>>) -> Self {
         Self { steps }
     }
 
-    pub fn run(&self, range: Option<CuboidsRange>) -> HashSet<Cuboid> {
+    pub fn run(&self, range: Option<Hyperrectangle<N>>) -> HashSet<Hyperrectangle<N>> {
         let mut current_cuboids = HashSet::new();
 
         for step in self.steps.iter().filter(|step| {
             if let Some(range) = &range {
-                range.cuboid_in(step.cuboid())
+                range.contains(step.cuboid())
             } else {
                 true
             }
@@ -29,31 +29,31 @@ impl InitializationProcedure {
     }
 
     fn process_single_step(
-        step: &RebootStep,
-        current_cuboids: &HashSet<Cuboid>,
-    ) -> HashSet<Cuboid> {
+        step: &RebootStep<N>,
+        current_cuboids: &HashSet<Hyperrectangle<N>>,
+    ) -> HashSet<Hyperrectangle<N>> {
         let (on, new_cuboid) = match step {
             RebootStep::On(cuboid) => (true, cuboid),
             RebootStep::Off(cuboid) => (false, cuboid),
         };
         let mut result = HashSet::new();
         if current_cuboids.is_empty() && on {
-            result.insert(new_cuboid.clone());
+            result.insert(*new_cuboid);
         }
 
         for current_cuboid in current_cuboids {
             if on {
-                if current_cuboid.overlaps(&new_cuboid) {
-                    result.extend(current_cuboid.split(&new_cuboid));
+                if current_cuboid.overlaps(new_cuboid) {
+                    result.extend(current_cuboid.split(new_cuboid));
                 } else {
-                    result.insert(current_cuboid.clone());
+                    result.insert(*current_cuboid);
                 }
-                result.insert(new_cuboid.clone());
+                result.insert(*new_cuboid);
             } else {
-                if current_cuboid.overlaps(&new_cuboid) {
-                    result.extend(current_cuboid.split(&new_cuboid));
+                if current_cuboid.overlaps(new_cuboid) {
+                    result.extend(current_cuboid.split(new_cuboid));
                 } else {
-                    result.insert(current_cuboid.clone());
+                    result.insert(*current_cuboid);
                 }
             }
         }
@@ -79,7 +79,7 @@ mod tests {
 
         let current_cuboids = HashSet::new();
 
-        let step_1 = RebootStep::from_str(instruction_1).unwrap();
+        let step_1 = RebootStep::<3>::from_str(instruction_1).unwrap();
         let current_cuboids =
             InitializationProcedure::process_single_step(&step_1, &current_cuboids);
         let result = current_cuboids
@@ -115,4 +115,42 @@ mod tests {
         let expected_result = 39;
         assert_eq!(result, expected_result);
     }
+
+    #[test]
+    fn test_run_2d() {
+        let steps = ["on x=0..3,y=0..3", "off x=1..2,y=1..2", "on x=2..5,y=2..2"]
+            .iter()
+            .map(|step| step.parse::<RebootStep<2>>().unwrap())
+            .collect();
+
+        let result = InitializationProcedure::new(steps).run(None);
+
+        let result = result
+            .iter()
+            .map(|square| square.count_cubes_on())
+            .sum::<usize>();
+        assert_eq!(result, 16 - 4 + 3);
+    }
+
+    #[test]
+    fn test_run_4d() {
+        let steps = [
+            "on x=0..1,y=0..1,z=0..1,w=0..1",
+            "on x=1..2,y=1..2,z=1..2,w=1..2",
+            "off x=0..0,y=0..0,z=0..0,w=0..0",
+            "on x=10..20,y=0..0,z=0..0,w=0..0",
+        ]
+        .iter()
+        .map(|step| step.parse::<RebootStep<4>>().unwrap())
+        .collect();
+        let range = Hyperrectangle::from_ranges([(0, 5), (0, 5), (0, 5), (0, 5)]);
+
+        let result = InitializationProcedure::new(steps).run(Some(range));
+
+        let result = result
+            .iter()
+            .map(|tesseract| tesseract.count_cubes_on())
+            .sum::<usize>();
+        assert_eq!(result, 16 + 16 - 1 - 1);
+    }
 }
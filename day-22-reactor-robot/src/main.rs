@@ -1,33 +1,45 @@
 mod cuboid;
-mod cuboids_range;
+mod export;
+mod hyperrectangle;
 mod initialization_procedure;
 mod parse_reboot_step_error;
 mod reboot_step;
+mod reboot_step_parser;
 
 use initialization_procedure::InitializationProcedure;
 
 use std::{
+    collections::HashSet,
+    env,
     fs::File,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, BufWriter, Write},
 };
 
-use cuboids_range::CuboidsRange;
+use cuboid::Cuboid;
+use export::VoxelSlice;
 
-fn load_data(file_name: &str) -> InitializationProcedure {
-    let file = File::open(file_name).expect(&format!("Can't read file {}", file_name));
+const INITIALIZATION_REGION: Cuboid = Cuboid::new((-50, 50), (-50, 50), (-50, 50));
+
+fn load_data<const N: usize>(file_name: &str) -> InitializationProcedure<N> {
+    let file = File::open(file_name).unwrap_or_else(|_| panic!("Can't read file {}", file_name));
     let file = BufReader::new(file);
 
     let steps = file
         .lines()
-        .map(|r_line| r_line.unwrap().parse().unwrap())
+        .enumerate()
+        .map(|(line_number, r_line)| {
+            r_line
+                .unwrap()
+                .parse()
+                .unwrap_or_else(|error| panic!("{}:{}: {}", file_name, line_number + 1, error))
+        })
         .collect();
-    InitializationProcedure { steps }
+    InitializationProcedure::new(steps)
 }
 
 fn part_1_result(file_name: &str) {
     let initialization_procedure = load_data(file_name);
-    let result_cuboids =
-        initialization_procedure.run(Some(CuboidsRange::new((-50, 50), (-50, 50), (-50, 50))));
+    let result_cuboids = initialization_procedure.run(Some(INITIALIZATION_REGION));
     let result = result_cuboids
         .iter()
         .fold(0, |sum, cuboid| sum + cuboid.count_cubes_on());
@@ -36,7 +48,7 @@ fn part_1_result(file_name: &str) {
 }
 
 fn part_2_result(file_name: &str) {
-    let initialization_procedure = load_data(file_name);
+    let initialization_procedure = load_data::<3>(file_name);
     let result_cuboids = initialization_procedure.run(None);
     let result = result_cuboids
         .iter()
@@ -45,15 +57,137 @@ fn part_2_result(file_name: &str) {
     println!("Part 2. Result: {}", result);
 }
 
+#[derive(Debug, Default)]
+struct ExportOptions {
+    init_region: bool,
+    merge: bool,
+    obj: Option<String>,
+    stl: Option<String>,
+    slice_z: Option<isize>,
+    slice_scale: Option<usize>,
+    pgm: Option<String>,
+    ppm: Option<String>,
+}
+
+impl ExportOptions {
+    fn from_args<I: Iterator<Item = String>>(mut args: I) -> Self {
+        let mut options = ExportOptions::default();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .unwrap_or_else(|| panic!("Missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "--init-region" => options.init_region = true,
+                "--merge" => options.merge = true,
+                "--obj" => options.obj = Some(value()),
+                "--stl" => options.stl = Some(value()),
+                "--slice-z" => options.slice_z = Some(value().parse().unwrap()),
+                "--slice-scale" => options.slice_scale = Some(value().parse().unwrap()),
+                "--pgm" => options.pgm = Some(value()),
+                "--ppm" => options.ppm = Some(value()),
+                _ => panic!("Unknown argument {}", arg),
+            }
+        }
+
+        // Options which only change exports would otherwise be ignored.
+        if (options.init_region || options.merge) && !options.any() {
+            panic!("--init-region and --merge need --obj, --stl, --pgm or --ppm");
+        }
+        if (options.slice_z.is_some() || options.slice_scale.is_some())
+            && options.pgm.is_none()
+            && options.ppm.is_none()
+        {
+            panic!("--slice-z and --slice-scale need --pgm or --ppm");
+        }
+        options
+    }
+
+    fn any(&self) -> bool {
+        self.obj.is_some() || self.stl.is_some() || self.pgm.is_some() || self.ppm.is_some()
+    }
+}
+
+fn create_output(file_name: &str) -> BufWriter<File> {
+    let file =
+        File::create(file_name).unwrap_or_else(|_| panic!("Can't create file {}", file_name));
+    BufWriter::new(file)
+}
+
+fn export_result(file_name: &str, options: &ExportOptions) {
+    let initialization_procedure = load_data(file_name);
+    let range = if options.init_region {
+        Some(INITIALIZATION_REGION)
+    } else {
+        None
+    };
+    let mut result_cuboids: HashSet<Cuboid> = initialization_procedure.run(range);
+    if options.merge {
+        result_cuboids = export::merge_cuboids(&result_cuboids);
+    }
+
+    if let Some(obj) = &options.obj {
+        let mut output = create_output(obj);
+        export::write_obj(&result_cuboids, &mut output).unwrap();
+        output.flush().unwrap();
+        println!("Exported {} cuboids to {}", result_cuboids.len(), obj);
+    }
+    if let Some(stl) = &options.stl {
+        let mut output = create_output(stl);
+        export::write_stl(&result_cuboids, &mut output).unwrap();
+        output.flush().unwrap();
+        println!("Exported {} cuboids to {}", result_cuboids.len(), stl);
+    }
+
+    if options.pgm.is_none() && options.ppm.is_none() {
+        return;
+    }
+    let z = options.slice_z.unwrap_or(0);
+    let slice = if options.init_region {
+        Some(VoxelSlice::new(
+            z,
+            (-50, 50),
+            (-50, 50),
+            options.slice_scale.unwrap_or(1),
+        ))
+    } else {
+        VoxelSlice::bounding(&result_cuboids, z, options.slice_scale.unwrap_or(1))
+    };
+    let slice = match slice {
+        Some(slice) => slice,
+        None => {
+            println!("No cubes are on at z={}", z);
+            return;
+        }
+    };
+    if let Some(pgm) = &options.pgm {
+        let mut output = create_output(pgm);
+        slice.write_pgm(&result_cuboids, &mut output).unwrap();
+        output.flush().unwrap();
+        println!("Exported slice z={} to {}", z, pgm);
+    }
+    if let Some(ppm) = &options.ppm {
+        let mut output = create_output(ppm);
+        slice.write_ppm(&result_cuboids, &mut output).unwrap();
+        output.flush().unwrap();
+        println!("Exported slice z={} to {}", z, ppm);
+    }
+}
+
 fn main() {
     const DATA_FILENAME: &str = "./resources/data.txt";
+    let export_options = ExportOptions::from_args(env::args().skip(1));
+    if export_options.any() {
+        export_result(DATA_FILENAME, &export_options);
+        return;
+    }
     part_1_result(DATA_FILENAME);
     part_2_result(DATA_FILENAME);
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{cuboid::Cuboid, cuboids_range::CuboidsRange, reboot_step::RebootStep};
+    use crate::reboot_step::RebootStep;
 
     use super::*;
 
@@ -98,8 +232,7 @@ mod tests {
     fn test_part_1() {
         const TEST_FILE_PATH: &str = "./resources/test_data.txt";
         let initialization_procedure = load_data(TEST_FILE_PATH);
-        let result_cuboids =
-            initialization_procedure.run(Some(CuboidsRange::new((-50, 50), (-50, 50), (-50, 50))));
+        let result_cuboids = initialization_procedure.run(Some(INITIALIZATION_REGION));
 
         let result = result_cuboids
             .iter()
@@ -110,7 +243,7 @@ mod tests {
     #[test]
     fn test_part_2() {
         const TEST_FILE_PATH: &str = "./resources/test_data_2.txt";
-        let initialization_procedure = load_data(TEST_FILE_PATH);
+        let initialization_procedure = load_data::<3>(TEST_FILE_PATH);
         let result_cuboids = initialization_procedure.run(None);
 
         let result = result_cuboids
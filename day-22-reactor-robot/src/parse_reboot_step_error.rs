@@ -1,2 +1,62 @@
-#[derive(Debug, Clone)]
-pub struct ParseRebootStepError(pub String);
+use std::fmt;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseRebootStepErrorKind {
+    Expected(&'static str),
+    UnknownInstruction(String),
+    UnknownAxis(String),
+    DuplicateAxis(String),
+    MissingAxis(String),
+    InvalidNumber(String),
+    InvertedRange {
+        axis: String,
+        min: isize,
+        max: isize,
+    },
+    TrailingInput(String),
+}
+
+// `column` is the 1-based position in the parsed line where the problem starts,
+// counted in characters.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseRebootStepError {
+    pub kind: ParseRebootStepErrorKind,
+    pub column: usize,
+}
+
+impl ParseRebootStepError {
+    pub fn new(kind: ParseRebootStepErrorKind, column: usize) -> Self {
+        Self { kind, column }
+    }
+}
+
+impl fmt::Display for ParseRebootStepErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Expected(expected) => write!(f, "expected {}", expected),
+            Self::UnknownInstruction(instruction) => write!(
+                f,
+                "unknown instruction '{}', expected 'on' or 'off'",
+                instruction
+            ),
+            Self::UnknownAxis(axis) => write!(f, "unknown axis '{}'", axis),
+            Self::DuplicateAxis(axis) => write!(f, "axis '{}' is given more than once", axis),
+            Self::MissingAxis(axis) => write!(f, "missing range for axis '{}'", axis),
+            Self::InvalidNumber(number) => write!(f, "'{}' is not a valid coordinate", number),
+            Self::InvertedRange { axis, min, max } => write!(
+                f,
+                "range {}={}..{} has its minimum above its maximum",
+                axis, min, max
+            ),
+            Self::TrailingInput(rest) => write!(f, "unexpected trailing input '{}'", rest),
+        }
+    }
+}
+
+impl fmt::Display for ParseRebootStepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.kind)
+    }
+}
+
+impl std::error::Error for ParseRebootStepError {}
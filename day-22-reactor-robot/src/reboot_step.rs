@@ -1,22 +1,25 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
 
-use crate::{cuboid::Cuboid, parse_reboot_step_error::ParseRebootStepError};
+use crate::{
+    hyperrectangle::Hyperrectangle, parse_reboot_step_error::ParseRebootStepError,
+    reboot_step_parser::RebootStepParser,
+};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
-pub enum RebootStep {
-    On(Cuboid),
-    Off(Cuboid),
+pub enum RebootStep<const N: usize> {
+    On(Hyperrectangle<N>),
+    Off(Hyperrectangle<N>),
 }
 
-impl RebootStep {
-    pub fn new_on(cuboid: Cuboid) -> Self {
+impl<const N: usize> RebootStep<N> {
+    pub fn new_on(cuboid: Hyperrectangle<N>) -> Self {
         Self::On(cuboid)
     }
-    pub fn new_off(cuboid: Cuboid) -> Self {
+    pub fn new_off(cuboid: Hyperrectangle<N>) -> Self {
         Self::Off(cuboid)
     }
 
-    pub fn cuboid(&self) -> &Cuboid {
+    pub fn cuboid(&self) -> &Hyperrectangle<N> {
         match self {
             Self::On(cuboid) => cuboid,
             Self::Off(cuboid) => cuboid,
@@ -24,45 +27,54 @@ impl RebootStep {
     }
 }
 
-impl FromStr for RebootStep {
+impl<const N: usize> FromStr for RebootStep<N> {
     type Err = ParseRebootStepError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut iter = s.split_ascii_whitespace();
-        let instruction = iter.next().ok_or(ParseRebootStepError(
-            "Can't get instruction field (on/off)".to_string(),
-        ))?;
-
-        let coordinate_range = iter
-            .next()
-            .ok_or(ParseRebootStepError(
-                "Can't get coordinates field".to_string(),
-            ))?
-            .parse()?;
+        RebootStepParser::new(s).reboot_step()
+    }
+}
 
-        Ok(match instruction {
-            "on" => Self::On(coordinate_range),
-            "off" => Self::Off(coordinate_range),
-            _ => return Err(ParseRebootStepError("Can't parse instruction".to_string())),
-        })
+impl<const N: usize> fmt::Display for RebootStep<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::On(cuboid) => write!(f, "on {}", cuboid),
+            Self::Off(cuboid) => write!(f, "off {}", cuboid),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{cuboid::Cuboid, reboot_step::RebootStep};
+    use crate::{cuboid::Cuboid, hyperrectangle::Hyperrectangle, reboot_step::RebootStep};
 
     #[test]
     fn test_reboot_step_from_str_on() {
         let string_repr = "on x=-20..26,y=-36..17,z=-47..7";
-        let parse_result = string_repr.parse::<RebootStep>().unwrap();
+        let parse_result = string_repr.parse::<RebootStep<3>>().unwrap();
         assert_eq!(
             parse_result,
-            RebootStep::On(Cuboid {
-                x: (-20, 26),
-                y: (-36, 17),
-                z: (-47, 7),
-            })
+            RebootStep::On(Cuboid::new((-20, 26), (-36, 17), (-47, 7)))
         );
     }
+
+    #[test]
+    fn test_reboot_step_from_str_2d() {
+        let string_repr = "off x=1..2,y=-3..4";
+        let parse_result = string_repr.parse::<RebootStep<2>>().unwrap();
+        assert_eq!(
+            parse_result,
+            RebootStep::Off(Hyperrectangle::from_ranges([(1, 2), (-3, 4)]))
+        );
+    }
+
+    #[test]
+    fn test_reboot_step_display_round_trip() {
+        let contents = std::fs::read_to_string("./resources/test_data.txt").unwrap();
+        for line in contents.lines() {
+            let step = line.parse::<RebootStep<3>>().unwrap();
+            assert_eq!(step.to_string(), line);
+            assert_eq!(step.to_string().parse::<RebootStep<3>>().unwrap(), step);
+        }
+    }
 }
@@ -0,0 +1,272 @@
+use crate::{
+    hyperrectangle::{axis_index, axis_name, Hyperrectangle},
+    parse_reboot_step_error::{ParseRebootStepError, ParseRebootStepErrorKind},
+    reboot_step::RebootStep,
+};
+
+// Grammar, whitespace is allowed between all tokens:
+//   step   := ("on" | "off") ranges
+//   ranges := range ("," range)*
+//   range  := axis "=" number ".." number
+// Ranges may come in any axis order, but every axis must be given exactly once.
+pub struct RebootStepParser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> RebootStepParser<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self { input, position: 0 }
+    }
+
+    pub fn reboot_step<const N: usize>(mut self) -> Result<RebootStep<N>, ParseRebootStepError> {
+        self.skip_whitespace();
+        let column = self.column();
+        let instruction = self.word();
+        let on = match instruction {
+            "on" => true,
+            "off" => false,
+            "" => return Err(self.error(ParseRebootStepErrorKind::Expected("'on' or 'off'"))),
+            _ => {
+                return Err(ParseRebootStepError::new(
+                    ParseRebootStepErrorKind::UnknownInstruction(instruction.to_string()),
+                    column,
+                ))
+            }
+        };
+
+        let cuboid = self.ranges()?;
+        self.end()?;
+
+        Ok(if on {
+            RebootStep::new_on(cuboid)
+        } else {
+            RebootStep::new_off(cuboid)
+        })
+    }
+
+    pub fn hyperrectangle<const N: usize>(
+        mut self,
+    ) -> Result<Hyperrectangle<N>, ParseRebootStepError> {
+        let hyperrectangle = self.ranges()?;
+        self.end()?;
+        Ok(hyperrectangle)
+    }
+
+    fn ranges<const N: usize>(&mut self) -> Result<Hyperrectangle<N>, ParseRebootStepError> {
+        let mut ranges = [None; N];
+        loop {
+            self.skip_whitespace();
+            let column = self.column();
+            let name = self.word();
+            if name.is_empty() {
+                return Err(self.error(ParseRebootStepErrorKind::Expected("axis name")));
+            }
+            let axis = axis_index(name).filter(|axis| *axis < N).ok_or_else(|| {
+                ParseRebootStepError::new(
+                    ParseRebootStepErrorKind::UnknownAxis(name.to_string()),
+                    column,
+                )
+            })?;
+            if ranges[axis].is_some() {
+                return Err(ParseRebootStepError::new(
+                    ParseRebootStepErrorKind::DuplicateAxis(name.to_string()),
+                    column,
+                ));
+            }
+
+            self.expect("=")?;
+            let min = self.integer()?;
+            self.expect("..")?;
+            let max = self.integer()?;
+            if min > max {
+                return Err(ParseRebootStepError::new(
+                    ParseRebootStepErrorKind::InvertedRange {
+                        axis: name.to_string(),
+                        min,
+                        max,
+                    },
+                    column,
+                ));
+            }
+            ranges[axis] = Some((min, max));
+
+            self.skip_whitespace();
+            if !self.consume(",") {
+                break;
+            }
+        }
+
+        let mut result = [(0, 0); N];
+        for (axis, range) in ranges.into_iter().enumerate() {
+            result[axis] = range.ok_or_else(|| {
+                self.error(ParseRebootStepErrorKind::MissingAxis(axis_name(axis)))
+            })?;
+        }
+        Ok(Hyperrectangle::from_ranges(result))
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    // `position` is a byte offset, columns count characters.
+    fn column(&self) -> usize {
+        self.input[..self.position].chars().count() + 1
+    }
+
+    fn error(&self, kind: ParseRebootStepErrorKind) -> ParseRebootStepError {
+        ParseRebootStepError::new(kind, self.column())
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn consume(&mut self, token: &str) -> bool {
+        if self.rest().starts_with(token) {
+            self.position += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &'static str) -> Result<(), ParseRebootStepError> {
+        self.skip_whitespace();
+        if self.consume(token) {
+            Ok(())
+        } else {
+            Err(self.error(ParseRebootStepErrorKind::Expected(token)))
+        }
+    }
+
+    fn take_while<P: Fn(usize, char) -> bool>(&mut self, predicate: P) -> &'a str {
+        let rest = self.rest();
+        let length = rest
+            .char_indices()
+            .find(|(index, c)| !predicate(*index, *c))
+            .map(|(index, _)| index)
+            .unwrap_or(rest.len());
+        self.position += length;
+        &rest[..length]
+    }
+
+    fn word(&mut self) -> &'a str {
+        self.take_while(|_, c| c.is_ascii_alphanumeric())
+    }
+
+    fn integer(&mut self) -> Result<isize, ParseRebootStepError> {
+        self.skip_whitespace();
+        let column = self.column();
+        let number = self
+            .take_while(|index, c| c.is_ascii_digit() || (index == 0 && (c == '-' || c == '+')));
+        if number.is_empty() {
+            return Err(self.error(ParseRebootStepErrorKind::Expected("number")));
+        }
+        number.parse().map_err(|_| {
+            ParseRebootStepError::new(
+                ParseRebootStepErrorKind::InvalidNumber(number.to_string()),
+                column,
+            )
+        })
+    }
+
+    fn end(&mut self) -> Result<(), ParseRebootStepError> {
+        self.skip_whitespace();
+        if self.rest().is_empty() {
+            Ok(())
+        } else {
+            Err(self.error(ParseRebootStepErrorKind::TrailingInput(
+                self.rest().to_string(),
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cuboid::Cuboid;
+
+    fn parse_error(input: &str) -> ParseRebootStepError {
+        RebootStepParser::new(input).reboot_step::<3>().unwrap_err()
+    }
+
+    #[test]
+    fn test_whitespace_and_axis_order() {
+        let step = RebootStepParser::new("  off  z = -47 .. 7 , x=-20..26,\ty=-36.. 17  ")
+            .reboot_step::<3>()
+            .unwrap();
+        assert_eq!(
+            step,
+            RebootStep::new_off(Cuboid::new((-20, 26), (-36, 17), (-47, 7)))
+        );
+    }
+
+    #[test]
+    fn test_error_locations() {
+        use ParseRebootStepErrorKind::*;
+
+        assert_eq!(
+            parse_error("toggle x=1..2,y=1..2,z=1..2"),
+            ParseRebootStepError::new(UnknownInstruction("toggle".to_string()), 1)
+        );
+        assert_eq!(
+            parse_error("on x=1..2,q=1..2,z=1..2"),
+            ParseRebootStepError::new(UnknownAxis("q".to_string()), 11)
+        );
+        assert_eq!(
+            parse_error("on x=1..2,x=1..2,z=1..2"),
+            ParseRebootStepError::new(DuplicateAxis("x".to_string()), 11)
+        );
+        assert_eq!(
+            parse_error("on x=1..2,z=1..2"),
+            ParseRebootStepError::new(MissingAxis("y".to_string()), 17)
+        );
+        assert_eq!(
+            parse_error("on x=1..2,y=5..-5,z=1..2"),
+            ParseRebootStepError::new(
+                InvertedRange {
+                    axis: "y".to_string(),
+                    min: 5,
+                    max: -5
+                },
+                11
+            )
+        );
+        assert_eq!(
+            parse_error("on x=1:2,y=1..2,z=1..2"),
+            ParseRebootStepError::new(Expected(".."), 7)
+        );
+        assert_eq!(
+            parse_error("on x=1..,y=1..2,z=1..2"),
+            ParseRebootStepError::new(Expected("number"), 9)
+        );
+        assert_eq!(
+            parse_error("on x=1..99999999999999999999,y=1..2,z=1..2"),
+            ParseRebootStepError::new(InvalidNumber("99999999999999999999".to_string()), 9)
+        );
+        assert_eq!(
+            parse_error("on x=1..2,y=1..2,z=1..2 extra"),
+            ParseRebootStepError::new(TrailingInput("extra".to_string()), 25)
+        );
+        assert_eq!(
+            parse_error("on x=1..2,y=1..2,z=1..2,w=1..2"),
+            ParseRebootStepError::new(UnknownAxis("w".to_string()), 25)
+        );
+        assert_eq!(
+            parse_error("on x=1..2,\u{a0}é=1..2,z=1..2"),
+            ParseRebootStepError::new(Expected("axis name"), 12)
+        );
+    }
+
+    #[test]
+    fn test_error_display() {
+        assert_eq!(
+            parse_error("on x=1..2,y=1..2").to_string(),
+            "column 17: missing range for axis 'z'"
+        );
+    }
+}
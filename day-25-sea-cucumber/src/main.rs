@@ -1,12 +1,21 @@
 use std::{
     collections::HashSet,
-    fs::File,
+    env,
+    fs::{self, File},
     hash::Hash,
     io::{BufRead, BufReader},
 };
 
+use common::visualisation::{Visualiser, VisualiserOptions};
+
+mod seafloor;
+mod stats;
+
+use seafloor::{Direction, Seafloor};
+use stats::StepStats;
+
 fn load_data(file_name: &str) -> (HashSet<SeaCucumber>, AreaSize) {
-    let file = File::open(file_name).expect(&format!("Can't read file {}", file_name));
+    let file = File::open(file_name).unwrap_or_else(|_| panic!("Can't read file {}", file_name));
     let file = BufReader::new(file);
 
     let mut area_size = AreaSize { x: 0, y: 0 };
@@ -25,7 +34,7 @@ fn load_data(file_name: &str) -> (HashSet<SeaCucumber>, AreaSize) {
                             '>' => Some(SeaCucumber::new_east_facing(x, y)),
                             'v' => Some(SeaCucumber::new_south_facing(x, y)),
                             '.' => None,
-                            _ => panic!(""),
+                            _ => panic!("Unexpected character '{}' in {}", c, file_name),
                         }
                     })
                     .collect::<HashSet<_>>()
@@ -83,6 +92,7 @@ impl SeaCucumber {
         SeaCucumber::SouthFacing { x, y }
     }
 
+    #[cfg(test)]
     fn move_forward(self, area_size: &AreaSize) -> Self {
         match self {
             SeaCucumber::EastFacing { x, y } => Self::new_east_facing((x + 1) % area_size.x, y),
@@ -91,6 +101,8 @@ impl SeaCucumber {
     }
 }
 
+// Reference implementation on sets of sea cucumbers.
+#[cfg(test)]
 mod solver {
     use crate::{AreaSize, SeaCucumber};
 
@@ -101,7 +113,7 @@ mod solver {
         area_size: &AreaSize,
     ) -> (HashSet<SeaCucumber>, usize) {
         for iter_num in 1.. {
-            let next_iteration = self::iteration(sea_cucumbers.clone(), &area_size);
+            let next_iteration = self::iteration(sea_cucumbers.clone(), area_size);
             if next_iteration == sea_cucumbers {
                 return (next_iteration, iter_num);
             }
@@ -111,7 +123,7 @@ mod solver {
         (HashSet::new(), 0)
     }
 
-    fn iteration(
+    pub fn iteration(
         sea_cucumbers: HashSet<SeaCucumber>,
         area_size: &AreaSize,
     ) -> HashSet<SeaCucumber> {
@@ -132,7 +144,7 @@ mod solver {
     }
 
     fn move_and_update(
-        facing_sea_cucumbers: &Vec<&SeaCucumber>,
+        facing_sea_cucumbers: &[&SeaCucumber],
         mut sea_cucumbers: HashSet<SeaCucumber>,
         area_size: &AreaSize,
     ) -> HashSet<SeaCucumber> {
@@ -142,22 +154,186 @@ mod solver {
                 moved.push(sea_cucumber);
             }
         }
-        moved.into_iter().for_each(|moved_cucumber| {
+        moved.iter().for_each(|moved_cucumber| {
             sea_cucumbers.remove(moved_cucumber);
         });
 
         sea_cucumbers
     }
 }
+
+fn load_seafloor(file_name: &str) -> Seafloor {
+    let input =
+        fs::read_to_string(file_name).unwrap_or_else(|_| panic!("Can't read file {}", file_name));
+    Seafloor::parse(&input).unwrap_or_else(|error| panic!("{}: {}", file_name, error))
+}
+
 fn part_1_result(file_name: &str) {
     let (sea_cucumbers, area_size) = load_data(file_name);
-    let result = solver::solve(sea_cucumbers, &area_size);
+    let steps = Seafloor::from_sea_cucumbers(&sea_cucumbers, area_size).run();
+
+    println!("Part 1. Result: {:?}", steps);
+}
+
+#[derive(Default)]
+struct SimulationOptions {
+    order: Option<Vec<Direction>>,
+    steps: Option<usize>,
+    stats: bool,
+    jams: bool,
+    visualiser: VisualiserOptions,
+}
 
-    println!("Part 1. Result: {:?}", result.1);
+impl SimulationOptions {
+    fn from_args<I: Iterator<Item = String>>(mut args: I) -> Self {
+        let mut options = SimulationOptions::default();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .unwrap_or_else(|| panic!("Missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "--order" => options.order = Some(parse_order(&value())),
+                "--steps" => options.steps = Some(value().parse().unwrap()),
+                "--stats" => options.stats = true,
+                "--jams" => options.jams = true,
+                _ => {
+                    if !options.visualiser.parse_arg(&arg, &mut args) {
+                        panic!("Unknown argument {}", arg)
+                    }
+                }
+            }
+        }
+        options
+    }
+
+    fn any(&self) -> bool {
+        self.order.is_some()
+            || self.steps.is_some()
+            || self.stats
+            || self.jams
+            || self.visualiser.any()
+    }
+}
+
+// Herd glyphs in moving order, e.g. `>v<^`.
+fn parse_order(order: &str) -> Vec<Direction> {
+    order
+        .chars()
+        .map(|c| {
+            Direction::from_glyph(c).unwrap_or_else(|| panic!("Unknown herd {} in {}", c, order))
+        })
+        .collect()
+}
+
+// Shows every step until the sea cucumbers stop moving or `max_steps` steps
+// were made. Returns the first step in which nobody moved, if any.
+fn animate(
+    seafloor: &mut Seafloor,
+    options: &SimulationOptions,
+    max_steps: usize,
+) -> Option<usize> {
+    let mut visualiser =
+        Visualiser::from_options(&options.visualiser).unwrap_or_else(|error| panic!("{}", error));
+
+    let mut moved = None;
+    let settled = loop {
+        let caption = match moved {
+            Some(moved) => format!("Step {}: {} moved", seafloor.steps(), moved),
+            None => format!("Step {}", seafloor.steps()),
+        };
+        visualiser.show(&*seafloor, &caption).unwrap();
+        if moved == Some(0) {
+            break Some(seafloor.steps());
+        }
+        if seafloor.steps() >= max_steps {
+            break None;
+        }
+        moved = Some(seafloor.step());
+    };
+
+    if let Some(summary) = visualiser.finish().unwrap() {
+        println!("{}", summary);
+    }
+    settled
+}
+
+const STEP_STATS_HEADER: &str = " step     >     v     <     ^  chain  max row  max column  stuck";
+
+fn print_step_stats(stats: &StepStats) {
+    let max = |densities: &[f64]| densities.iter().copied().fold(0.0, f64::max);
+    println!(
+        "{:>5} {:>5} {:>5} {:>5} {:>5}  {:>5}  {:>7.2}  {:>10.2}  {:>5}",
+        stats.step,
+        stats.moves[Direction::East as usize],
+        stats.moves[Direction::South as usize],
+        stats.moves[Direction::West as usize],
+        stats.moves[Direction::North as usize],
+        stats.longest_blocked_chain,
+        max(&stats.row_density),
+        max(&stats.column_density),
+        stats.stuck
+    );
+}
+
+fn print_jams(seafloor: &Seafloor) {
+    let gridlock = stats::find_gridlock(seafloor);
+    let stuck = gridlock.stuck.iter().filter(|stuck| **stuck).count();
+    println!(
+        "After step {}: {} sea cucumbers can never move again, in {} regions",
+        seafloor.steps(),
+        stuck,
+        gridlock.regions.len()
+    );
+    for region in &gridlock.regions {
+        println!("{:>5} from {},{}", region.len(), region[0].0, region[0].1);
+    }
+}
+
+fn simulation_result(file_name: &str, options: &SimulationOptions) {
+    let mut seafloor = load_seafloor(file_name);
+    if let Some(order) = &options.order {
+        seafloor = seafloor.with_order(order.clone());
+    }
+
+    let max_steps = options.steps.unwrap_or(usize::MAX);
+    let settled = if options.visualiser.any() {
+        animate(&mut seafloor, options, max_steps)
+    } else if options.stats {
+        println!("{}", STEP_STATS_HEADER);
+        let mut settled = None;
+        while settled.is_none() && seafloor.steps() < max_steps {
+            let stats = stats::step_with_stats(&mut seafloor);
+            print_step_stats(&stats);
+            if stats.moved() == 0 {
+                settled = Some(stats.step);
+            }
+        }
+        settled
+    } else {
+        seafloor.run_for(max_steps)
+    };
+
+    if options.jams {
+        print_jams(&seafloor);
+    }
+    if let Some(step) = settled {
+        println!("No sea cucumber moves in step {}", step);
+    } else {
+        println!(
+            "Stopped after {} steps, sea cucumbers still moving",
+            seafloor.steps()
+        );
+    }
 }
 
 fn main() {
     const DATA_FILENAME: &str = "./resources/data.txt";
+    let simulation_options = SimulationOptions::from_args(env::args().skip(1));
+    if simulation_options.any() {
+        simulation_result(DATA_FILENAME, &simulation_options);
+        return;
+    }
     part_1_result(DATA_FILENAME);
 }
 
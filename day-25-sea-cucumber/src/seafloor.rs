@@ -0,0 +1,388 @@
+use std::collections::HashSet;
+
+use common::{
+    grid::{Coordinates, Grid, ParseGridError},
+    visualisation::Render,
+};
+
+use crate::{AreaSize, SeaCucumber};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Direction {
+    East,
+    South,
+    West,
+    North,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::East,
+        Direction::South,
+        Direction::West,
+        Direction::North,
+    ];
+
+    pub fn from_glyph(c: char) -> Option<Self> {
+        match c {
+            '>' => Some(Direction::East),
+            'v' => Some(Direction::South),
+            '<' => Some(Direction::West),
+            '^' => Some(Direction::North),
+            _ => None,
+        }
+    }
+
+    pub fn glyph(self) -> char {
+        match self {
+            Direction::East => '>',
+            Direction::South => 'v',
+            Direction::West => '<',
+            Direction::North => '^',
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+            Direction::North => Direction::South,
+        }
+    }
+
+    fn offset(self) -> (isize, isize) {
+        match self {
+            Direction::East => (0, 1),
+            Direction::South => (1, 0),
+            Direction::West => (0, -1),
+            Direction::North => (-1, 0),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Cell {
+    Empty,
+    // Never moves and blocks every herd.
+    Obstacle,
+    SeaCucumber(Direction),
+}
+
+impl Cell {
+    fn parse(c: char) -> Option<Self> {
+        match c {
+            '.' => Some(Cell::Empty),
+            '#' => Some(Cell::Obstacle),
+            _ => Direction::from_glyph(c).map(Cell::SeaCucumber),
+        }
+    }
+
+    fn glyph(self) -> char {
+        match self {
+            Cell::Empty => '.',
+            Cell::Obstacle => '#',
+            Cell::SeaCucumber(direction) => direction.glyph(),
+        }
+    }
+}
+
+// The sea floor as a dense grid, `x` being the row. Every herd moves from the
+// current grid into a second one, which then becomes the current grid, so no
+// state is allocated or compared while stepping. Between herds both grids are
+// the same.
+#[derive(Debug, Clone)]
+pub struct Seafloor {
+    cells: Grid<Cell>,
+    buffer: Grid<Cell>,
+    // Moves of the last herd, replayed on the buffer to bring it up to date.
+    moves: Vec<(Coordinates, Coordinates)>,
+    // Herds in the order they move within a step.
+    order: Vec<Direction>,
+    // Sea cucumbers never turn, so empty herds can be skipped.
+    herd_sizes: [usize; 4],
+    steps: usize,
+}
+
+impl Seafloor {
+    // Herds move east, south, west and then north, so the puzzle's east and
+    // south facing herds move as described.
+    pub fn new(cells: Grid<Cell>) -> Self {
+        let mut herd_sizes = [0; 4];
+        for cell in cells.iter() {
+            if let Cell::SeaCucumber(direction) = cell {
+                herd_sizes[*direction as usize] += 1;
+            }
+        }
+        Self {
+            herd_sizes,
+            buffer: cells.clone(),
+            cells,
+            moves: Vec::new(),
+            order: Direction::ALL.to_vec(),
+            steps: 0,
+        }
+    }
+
+    pub fn parse(input: &str) -> Result<Self, ParseGridError> {
+        Ok(Self::new(Grid::parse_chars(input, Cell::parse)?))
+    }
+
+    pub fn from_sea_cucumbers(sea_cucumbers: &HashSet<SeaCucumber>, area_size: AreaSize) -> Self {
+        let mut cells = Grid::new(area_size.x, area_size.y, Cell::Empty);
+        for sea_cucumber in sea_cucumbers {
+            let (x, y, direction) = match *sea_cucumber {
+                SeaCucumber::EastFacing { x, y } => (x, y, Direction::East),
+                SeaCucumber::SouthFacing { x, y } => (x, y, Direction::South),
+            };
+            cells[(y, x)] = Cell::SeaCucumber(direction);
+        }
+        Self::new(cells)
+    }
+
+    // Herds left out of `order` never move, a herd listed twice moves twice
+    // per step.
+    pub fn with_order(mut self, order: Vec<Direction>) -> Self {
+        self.order = order;
+        self
+    }
+
+    // Whether `herd` is in the order, herds left out never move.
+    pub fn herd_moves(&self, herd: Direction) -> bool {
+        self.order.contains(&herd)
+    }
+
+    pub fn cells(&self) -> &Grid<Cell> {
+        &self.cells
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    // Moves every herd in order. Returns how many sea cucumbers moved.
+    pub fn step(&mut self) -> usize {
+        self.step_by_herd().iter().sum()
+    }
+
+    // Same as `step`, counting moves of every herd, indexed by
+    // `Direction as usize`.
+    pub fn step_by_herd(&mut self) -> [usize; 4] {
+        let mut moves = [0; 4];
+        for index in 0..self.order.len() {
+            let herd = self.order[index];
+            if self.herd_sizes[herd as usize] > 0 {
+                moves[herd as usize] += self.move_herd(herd);
+            }
+        }
+        self.steps += 1;
+        moves
+    }
+
+    // The neighbouring cell in `direction`, the sea floor wrapping around.
+    pub fn ahead(&self, (x, y): Coordinates, direction: Direction) -> Coordinates {
+        let (x_size, y_size) = self.cells.size();
+        let (dx, dy) = direction.offset();
+        (wrap(x, dx, x_size), wrap(y, dy, y_size))
+    }
+
+    // Steps until nobody moves, returning the number of the first step in
+    // which nobody moved. Flows which never jam, like `>.`, run forever, see
+    // `run_for`.
+    pub fn run(&mut self) -> usize {
+        while self.step() > 0 {}
+        self.steps
+    }
+
+    // Same as `run`, but stops once `max_steps` steps were made. Returns
+    // `None` if the sea cucumbers were still moving by then.
+    pub fn run_for(&mut self, max_steps: usize) -> Option<usize> {
+        while self.steps < max_steps {
+            if self.step() == 0 {
+                return Some(self.steps);
+            }
+        }
+        None
+    }
+
+    fn move_herd(&mut self, herd: Direction) -> usize {
+        self.moves.clear();
+        for (x, y) in self.cells.coordinates() {
+            if self.cells[(x, y)] != Cell::SeaCucumber(herd) {
+                continue;
+            }
+            let next = self.ahead((x, y), herd);
+            if self.cells[next] == Cell::Empty {
+                self.buffer[(x, y)] = Cell::Empty;
+                self.buffer[next] = Cell::SeaCucumber(herd);
+                self.moves.push(((x, y), next));
+            }
+        }
+
+        std::mem::swap(&mut self.cells, &mut self.buffer);
+        for (from, to) in &self.moves {
+            self.buffer[*from] = Cell::Empty;
+            self.buffer[*to] = Cell::SeaCucumber(herd);
+        }
+        self.moves.len()
+    }
+}
+
+// `value + offset` on a ring of `size` values, `offset` being at most 1 away
+// from 0.
+fn wrap(value: usize, offset: isize, size: usize) -> usize {
+    match offset {
+        1 if value + 1 == size => 0,
+        1 => value + 1,
+        -1 if value == 0 => size - 1,
+        -1 => value - 1,
+        _ => value,
+    }
+}
+
+impl Render for Seafloor {
+    fn size(&self) -> (usize, usize) {
+        self.cells.size()
+    }
+
+    fn glyph(&self, coordinates: Coordinates) -> char {
+        self.cells[coordinates].glyph()
+    }
+
+    fn colour(&self, coordinates: Coordinates) -> [u8; 3] {
+        match self.cells[coordinates] {
+            Cell::SeaCucumber(Direction::East) => [250, 140, 60],
+            Cell::SeaCucumber(Direction::South) => [90, 200, 120],
+            Cell::SeaCucumber(Direction::West) => [230, 90, 160],
+            Cell::SeaCucumber(Direction::North) => [90, 170, 250],
+            Cell::Obstacle => [120, 120, 120],
+            Cell::Empty => [20, 40, 90],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Instant};
+
+    use super::*;
+    use crate::{load_data, solver};
+
+    fn load_seafloor(file_name: &str) -> Seafloor {
+        Seafloor::parse(&fs::read_to_string(file_name).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_step() {
+        let mut seafloor =
+            Seafloor::parse("...>...\n.......\n......>\nv.....>\n......>\n.......\n..vvv..")
+                .unwrap();
+
+        assert_eq!(seafloor.step(), 5);
+
+        assert_eq!(
+            seafloor.cells().map(|cell| cell.glyph()).to_string(),
+            "..vv>..\n.......\n>......\nv.....>\n>......\n.......\n....v.."
+        );
+        assert_eq!(seafloor.glyph((0, 4)), '>');
+        assert_eq!(seafloor.colour((0, 2)), [90, 200, 120]);
+    }
+
+    #[test]
+    fn test_run() {
+        let mut seafloor = load_seafloor("./resources/test_data.txt");
+
+        assert_eq!(seafloor.run(), 58);
+        assert_eq!(seafloor.step(), 0);
+    }
+
+    #[test]
+    fn test_run_for() {
+        let mut settling = load_seafloor("./resources/test_data.txt");
+        let mut flowing = Seafloor::parse(">.").unwrap();
+
+        assert_eq!(settling.run_for(57), None);
+        assert_eq!(settling.run_for(100), Some(58));
+        assert_eq!(flowing.run_for(10), None);
+        assert_eq!(flowing.steps(), 10);
+    }
+
+    #[test]
+    fn test_matches_sea_cucumbers() {
+        let (sea_cucumbers, area_size) = load_data("./resources/test_load_data.txt");
+
+        let seafloor = Seafloor::from_sea_cucumbers(&sea_cucumbers, area_size);
+
+        assert_eq!(
+            seafloor.cells(),
+            load_seafloor("./resources/test_load_data.txt").cells()
+        );
+    }
+
+    // Run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn benchmark_run() {
+        let (sea_cucumbers, area_size) = load_data("./resources/data.txt");
+
+        let start = Instant::now();
+        let (_, expected) = solver::solve(sea_cucumbers.clone(), &area_size);
+        let set_time = start.elapsed();
+
+        let start = Instant::now();
+        let steps = Seafloor::from_sea_cucumbers(&sea_cucumbers, area_size).run();
+        let grid_time = start.elapsed();
+
+        assert_eq!(steps, expected);
+        println!(
+            "{} steps: sets {:?}, dense grid {:?} ({:.1}x faster)",
+            steps,
+            set_time,
+            grid_time,
+            set_time.as_secs_f64() / grid_time.as_secs_f64()
+        );
+    }
+
+    fn glyphs(seafloor: &Seafloor) -> String {
+        seafloor.cells().map(|cell| cell.glyph()).to_string()
+    }
+
+    #[test]
+    fn test_all_directions() {
+        let mut seafloor = Seafloor::parse("..<.\n^...\n....\n").unwrap();
+
+        assert_eq!(seafloor.step(), 2);
+
+        assert_eq!(glyphs(&seafloor), "^<..\n....\n....");
+    }
+
+    #[test]
+    fn test_obstacles() {
+        let mut seafloor = Seafloor::parse(">.#>\n.v..\n.#..").unwrap();
+
+        assert_eq!(seafloor.step(), 1);
+        assert_eq!(glyphs(&seafloor), ".>#>\n.v..\n.#..");
+        assert_eq!(seafloor.run(), 3);
+        assert_eq!(glyphs(&seafloor), ">>#.\n.v..\n.#..");
+    }
+
+    #[test]
+    fn test_move_order() {
+        let input = ">.\nv.";
+        let mut east_first = Seafloor::parse(input).unwrap();
+        let mut south_first = Seafloor::parse(input)
+            .unwrap()
+            .with_order(vec![Direction::South, Direction::East]);
+        let mut east_only = Seafloor::parse(input)
+            .unwrap()
+            .with_order(vec![Direction::East]);
+
+        east_first.step();
+        south_first.step();
+        east_only.step();
+
+        assert_eq!(glyphs(&east_first), "v>\n..");
+        assert_eq!(glyphs(&south_first), ".>\nv.");
+        assert_eq!(glyphs(&east_only), ".>\nv.");
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::VecDeque;
+
+use common::grid::{Coordinates, Grid};
+
+use crate::seafloor::{Cell, Direction, Seafloor};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepStats {
+    pub step: usize,
+    // Indexed by `Direction as usize`.
+    pub moves: [usize; 4],
+    // Share of every row's and every column's cells taken by sea cucumbers.
+    pub row_density: Vec<f64>,
+    pub column_density: Vec<f64>,
+    pub longest_blocked_chain: usize,
+    pub stuck: usize,
+}
+
+impl StepStats {
+    pub fn moved(&self) -> usize {
+        self.moves.iter().sum()
+    }
+}
+
+// Makes a step and describes the sea floor after it.
+pub fn step_with_stats(seafloor: &mut Seafloor) -> StepStats {
+    let moves = seafloor.step_by_herd();
+    let cells = seafloor.cells();
+    let (x_size, y_size) = cells.size();
+
+    let mut row_counts = vec![0; x_size];
+    let mut column_counts = vec![0; y_size];
+    for ((x, y), cell) in cells.cells() {
+        if let Cell::SeaCucumber(_) = cell {
+            row_counts[x] += 1;
+            column_counts[y] += 1;
+        }
+    }
+    let density = |counts: Vec<usize>, size: usize| {
+        counts
+            .into_iter()
+            .map(|count| count as f64 / size as f64)
+            .collect()
+    };
+
+    StepStats {
+        step: seafloor.steps(),
+        moves,
+        row_density: density(row_counts, y_size),
+        column_density: density(column_counts, x_size),
+        longest_blocked_chain: longest_blocked_chain(seafloor),
+        stuck: find_gridlock(seafloor)
+            .stuck
+            .iter()
+            .filter(|stuck| **stuck)
+            .count(),
+    }
+}
+
+// Longest line of sea cucumbers of one herd, each behind the next, whose
+// first one is blocked by an obstacle or a sea cucumber of another herd. A
+// row or column taken entirely by one herd counts as a chain too.
+pub fn longest_blocked_chain(seafloor: &Seafloor) -> usize {
+    let cells = seafloor.cells();
+    let (x_size, y_size) = cells.size();
+    let mut longest = 0;
+
+    for (coordinates, cell) in cells.cells() {
+        let Cell::SeaCucumber(herd) = *cell else {
+            continue;
+        };
+        let ahead = cells[seafloor.ahead(coordinates, herd)];
+        if ahead == Cell::Empty || ahead == *cell {
+            continue;
+        }
+
+        let mut length = 1;
+        let mut behind = seafloor.ahead(coordinates, herd.opposite());
+        while cells[behind] == *cell && behind != coordinates {
+            length += 1;
+            behind = seafloor.ahead(behind, herd.opposite());
+        }
+        longest = longest.max(length);
+    }
+
+    let horizontal = [Direction::East, Direction::West];
+    let vertical = [Direction::South, Direction::North];
+    for row in cells.rows() {
+        if taken_by_herd(row, horizontal) {
+            longest = longest.max(y_size);
+        }
+    }
+    for y in 0..y_size {
+        let column = (0..x_size).map(|x| cells[(x, y)]).collect::<Vec<_>>();
+        if taken_by_herd(&column, vertical) {
+            longest = longest.max(x_size);
+        }
+    }
+
+    longest
+}
+
+// Whether the whole line holds one of the herds moving along it.
+fn taken_by_herd(line: &[Cell], herds: [Direction; 2]) -> bool {
+    herds
+        .iter()
+        .any(|herd| !line.is_empty() && line.iter().all(|cell| *cell == Cell::SeaCucumber(*herd)))
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Gridlock {
+    // Sea cucumbers which can never move again.
+    pub stuck: Grid<bool>,
+    // Connected groups of stuck sea cucumbers, the sea floor wrapping around,
+    // in the order they are met row by row.
+    pub regions: Vec<Vec<Coordinates>>,
+}
+
+// A sea cucumber can never move again if its herd isn't in the order, or if
+// the cell ahead of it holds an obstacle or a sea cucumber which can never move
+// again. Starting from all sea cucumbers, those of moving herds with a free
+// cell ahead are released, then those waiting behind released ones, until only
+// stuck ones remain.
+pub fn find_gridlock(seafloor: &Seafloor) -> Gridlock {
+    let cells = seafloor.cells();
+    let mut stuck = cells.map(|cell| matches!(cell, Cell::SeaCucumber(_)));
+
+    let mut released = VecDeque::new();
+    for (coordinates, cell) in cells.cells() {
+        if let Cell::SeaCucumber(herd) = cell {
+            if seafloor.herd_moves(*herd)
+                && cells[seafloor.ahead(coordinates, *herd)] == Cell::Empty
+            {
+                stuck[coordinates] = false;
+                released.push_back(coordinates);
+            }
+        }
+    }
+    while let Some(free) = released.pop_front() {
+        for herd in Direction::ALL
+            .into_iter()
+            .filter(|herd| seafloor.herd_moves(*herd))
+        {
+            let behind = seafloor.ahead(free, herd.opposite());
+            if stuck[behind] && cells[behind] == Cell::SeaCucumber(herd) {
+                stuck[behind] = false;
+                released.push_back(behind);
+            }
+        }
+    }
+
+    let mut in_region = Grid::new(stuck.width(), stuck.height(), false);
+    let mut regions = Vec::new();
+    for start in stuck.coordinates() {
+        if !stuck[start] || in_region[start] {
+            continue;
+        }
+        in_region[start] = true;
+        let mut region = vec![start];
+        let mut to_check = VecDeque::from(vec![start]);
+        while let Some(front) = to_check.pop_front() {
+            for direction in Direction::ALL {
+                let adjacent = seafloor.ahead(front, direction);
+                if stuck[adjacent] && !in_region[adjacent] {
+                    in_region[adjacent] = true;
+                    region.push(adjacent);
+                    to_check.push_back(adjacent);
+                }
+            }
+        }
+        region.sort_unstable();
+        regions.push(region);
+    }
+
+    Gridlock { stuck, regions }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_with_stats() {
+        let mut seafloor =
+            Seafloor::parse("...>...\n.......\n......>\nv.....>\n......>\n.......\n..vvv..")
+                .unwrap();
+
+        let stats = step_with_stats(&mut seafloor);
+
+        assert_eq!(stats.step, 1);
+        assert_eq!(stats.moves, [3, 2, 0, 0]);
+        assert_eq!(stats.moved(), 5);
+        assert_eq!(stats.row_density[0], 3.0 / 7.0);
+        assert_eq!(stats.column_density[0], 3.0 / 7.0);
+        assert_eq!(stats.longest_blocked_chain, 1);
+        assert_eq!(stats.stuck, 0);
+    }
+
+    #[test]
+    fn test_longest_blocked_chain() {
+        let blocked = Seafloor::parse(">>>v.\n.....\n<<#..").unwrap();
+        let free = Seafloor::parse(">>>..\n.....").unwrap();
+        let full_column = Seafloor::parse("v.\nv.\nv.").unwrap();
+
+        assert_eq!(longest_blocked_chain(&blocked), 3);
+        assert_eq!(longest_blocked_chain(&free), 0);
+        assert_eq!(longest_blocked_chain(&full_column), 3);
+    }
+
+    #[test]
+    fn test_find_gridlock() {
+        let seafloor = Seafloor::parse(">v...\n^<...\n>>...\n.>>#.\n.....").unwrap();
+
+        let gridlock = find_gridlock(&seafloor);
+
+        assert_eq!(
+            gridlock.regions,
+            vec![vec![(0, 0), (0, 1), (1, 0), (1, 1)], vec![(3, 1), (3, 2)]]
+        );
+        assert!(!gridlock.stuck[(2, 0)]);
+        assert!(!gridlock.stuck[(2, 1)]);
+    }
+
+    #[test]
+    fn test_gridlock_of_herds_left_out_of_order() {
+        let seafloor = Seafloor::parse(">v..\n....\n.>..\n....")
+            .unwrap()
+            .with_order(vec![Direction::East]);
+
+        let gridlock = find_gridlock(&seafloor);
+
+        assert_eq!(gridlock.regions, vec![vec![(0, 0), (0, 1)]]);
+        assert!(!gridlock.stuck[(2, 1)]);
+    }
+
+    #[test]
+    fn test_stuck_after_stopping() {
+        let mut seafloor = Seafloor::parse(">>#\n...").unwrap();
+
+        assert_eq!(find_gridlock(&seafloor).regions, vec![vec![(0, 0), (0, 1)]]);
+        assert_eq!(step_with_stats(&mut seafloor).stuck, 2);
+        assert_eq!(
+            find_gridlock(&Seafloor::parse(">.\n..").unwrap()).regions,
+            Vec::<Vec<Coordinates>>::new()
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use std::io::{self, Write};
+
+use common::{
+    grid::{Coordinates, Grid, Neighbourhood},
+    pathfinding::flood_fill,
+};
+
+use crate::Area;
+
+pub type BasinId = usize;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BasinMode {
+    // Basins are areas enclosed by walls.
+    Walls,
+    // Every cell flows to its lowest neighbour if that one is lower, basins
+    // are the sets of cells draining into the same low point. Connected cells
+    // of equal height flow as one through their lowest exit, and form a
+    // single low point, like a flat valley floor, if they have none.
+    Drainage,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct BasinRules {
+    // Cells this high or higher don't belong to any basin.
+    pub wall_height: u8,
+    pub neighbourhood: Neighbourhood,
+    pub mode: BasinMode,
+}
+
+impl Default for BasinRules {
+    fn default() -> Self {
+        Self {
+            wall_height: 9,
+            neighbourhood: Neighbourhood::Four,
+            mode: BasinMode::Walls,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Basin {
+    pub id: BasinId,
+    pub size: usize,
+    pub low_point: Coordinates,
+    pub min_height: u8,
+    pub max_height: u8,
+    pub height_sum: usize,
+}
+
+impl Basin {
+    fn new(id: BasinId, low_point: Coordinates, height: u8) -> Self {
+        Self {
+            id,
+            size: 0,
+            low_point,
+            min_height: height,
+            max_height: height,
+            height_sum: 0,
+        }
+    }
+
+    fn add(&mut self, coordinates: Coordinates, height: u8) {
+        if height < self.min_height {
+            self.min_height = height;
+            self.low_point = coordinates;
+        }
+        self.max_height = self.max_height.max(height);
+        self.size += 1;
+        self.height_sum += height as usize;
+    }
+
+    // Height difference between the basin's rim and its low point.
+    pub fn depth(&self) -> u8 {
+        self.max_height - self.min_height
+    }
+
+    pub fn mean_height(&self) -> f64 {
+        self.height_sum as f64 / self.size as f64
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Segmentation {
+    // Basin of every cell, `None` for walls.
+    pub labels: Grid<Option<BasinId>>,
+    // Indexed by basin id. Ids are given in the order the basins are first met
+    // when scanning the map row by row.
+    pub basins: Vec<Basin>,
+}
+
+impl Segmentation {
+    pub fn basin_at(&self, coordinates: Coordinates) -> Option<&Basin> {
+        self.labels
+            .get(coordinates)
+            .copied()
+            .flatten()
+            .map(|id| &self.basins[id])
+    }
+
+    pub fn largest(&self, n: usize) -> Vec<&Basin> {
+        let mut basins = self.basins.iter().collect::<Vec<_>>();
+        basins.sort_by_key(|basin| (std::cmp::Reverse(basin.size), basin.id));
+        basins.truncate(n);
+        basins
+    }
+}
+
+pub fn segment(area: &Area, rules: &BasinRules) -> Segmentation {
+    let labels = match rules.mode {
+        BasinMode::Walls => label_walled_basins(area, rules),
+        BasinMode::Drainage => label_drainage_basins(area, rules),
+    };
+
+    let mut basins: Vec<Basin> = Vec::new();
+    for (coordinates, label) in labels.cells() {
+        if let Some(id) = *label {
+            if id == basins.len() {
+                basins.push(Basin::new(id, coordinates, area[coordinates]));
+            }
+            basins[id].add(coordinates, area[coordinates]);
+        }
+    }
+
+    Segmentation { labels, basins }
+}
+
+fn label_walled_basins(area: &Area, rules: &BasinRules) -> Grid<Option<BasinId>> {
+    let mut labels = Grid::new(area.width(), area.height(), None);
+    let mut basins_count = 0;
+
+    for start in area.coordinates() {
+        if area[start] >= rules.wall_height || labels[start].is_some() {
+            continue;
+        }
+
+        labels[start] = Some(basins_count);
+        flood_fill(area.size(), [start], rules.neighbourhood, |adjacent| {
+            let enter = area[adjacent] < rules.wall_height && labels[adjacent].is_none();
+            if enter {
+                labels[adjacent] = Some(basins_count);
+            }
+            enter
+        });
+        basins_count += 1;
+    }
+
+    labels
+}
+
+fn label_drainage_basins(area: &Area, rules: &BasinRules) -> Grid<Option<BasinId>> {
+    // Connected cells of equal height drain together through their lowest
+    // exit, ties going to the exit met first breadth first from the plateau's
+    // first cell in row order. A plateau without a lower exit is a low point,
+    // and every cell draining into it is labelled with its first cell.
+    let mut sinks = Grid::new(area.width(), area.height(), None);
+    let mut exits = Grid::new(area.width(), area.height(), None);
+    let mut on_plateau = Grid::new(area.width(), area.height(), false);
+    for start in area.coordinates() {
+        if area[start] >= rules.wall_height || on_plateau[start] {
+            continue;
+        }
+
+        on_plateau[start] = true;
+        let plateau = flood_fill(area.size(), [start], rules.neighbourhood, |adjacent| {
+            let enter = !on_plateau[adjacent] && area[adjacent] == area[start];
+            if enter {
+                on_plateau[adjacent] = true;
+            }
+            enter
+        });
+        let exit = plateau
+            .iter()
+            .flat_map(|cell| area.neighbours(*cell, rules.neighbourhood))
+            .filter(|adjacent| area[*adjacent] < area[start])
+            .min_by_key(|adjacent| area[*adjacent]);
+        for cell in plateau {
+            match exit {
+                Some(exit) => exits[cell] = Some(exit),
+                None => sinks[cell] = Some(start),
+            }
+        }
+    }
+
+    for start in area.coordinates() {
+        if area[start] >= rules.wall_height {
+            continue;
+        }
+
+        // Lower cells are never walls and every plateau without an exit is
+        // already labelled, so the way down ends at a labelled cell.
+        let mut downhill = vec![start];
+        let mut sink = start;
+        while sinks[sink].is_none() {
+            sink = exits[sink].unwrap();
+            downhill.push(sink);
+        }
+        let sink = sinks[sink];
+        for cell in downhill {
+            sinks[cell] = sink;
+        }
+    }
+
+    // Ids follow the order in which basins are met, as for walled basins.
+    let mut ids = Grid::new(area.width(), area.height(), None);
+    let mut basins_count = 0;
+    sinks.map(|sink| {
+        sink.map(|sink| {
+            *ids[sink].get_or_insert_with(|| {
+                basins_count += 1;
+                basins_count - 1
+            })
+        })
+    })
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RenderStyle {
+    // Heights on a background colour picked per basin, walls dimmed.
+    Ansi,
+    // A letter per basin, cycling through the alphabet, walls as `#`.
+    Plain,
+}
+
+const BASIN_LETTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+// 256-colour palette entries which stay readable with black text.
+const BASIN_COLOURS: [u8; 12] = [117, 150, 186, 216, 183, 152, 222, 159, 194, 224, 147, 229];
+
+pub fn write_basins<W: Write>(
+    area: &Area,
+    segmentation: &Segmentation,
+    style: RenderStyle,
+    writer: &mut W,
+) -> io::Result<()> {
+    for (x, row) in segmentation.labels.rows().enumerate() {
+        for (y, label) in row.iter().enumerate() {
+            let height = area[(x, y)];
+            match (style, label) {
+                (RenderStyle::Ansi, Some(id)) => write!(
+                    writer,
+                    "\x1b[30;48;5;{}m{}\x1b[0m",
+                    BASIN_COLOURS[id % BASIN_COLOURS.len()],
+                    height
+                )?,
+                (RenderStyle::Ansi, None) => write!(writer, "\x1b[2m{}\x1b[0m", height)?,
+                (RenderStyle::Plain, Some(id)) => write!(
+                    writer,
+                    "{}",
+                    BASIN_LETTERS[id % BASIN_LETTERS.len()] as char
+                )?,
+                (RenderStyle::Plain, None) => write!(writer, "#")?,
+            }
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_data;
+
+    #[test]
+    fn test_segment() {
+        let data = load_data("./resources/test_data.txt");
+
+        let segmentation = segment(&data, &BasinRules::default());
+
+        assert_eq!(segmentation.basins.len(), 4);
+        assert_eq!(
+            segmentation
+                .basins
+                .iter()
+                .map(|basin| (basin.size, basin.low_point, basin.min_height))
+                .collect::<Vec<_>>(),
+            vec![
+                (3, (0, 1), 1),
+                (9, (0, 9), 0),
+                (14, (2, 2), 5),
+                (9, (4, 6), 5)
+            ]
+        );
+        assert_eq!(
+            segmentation
+                .basins
+                .iter()
+                .map(Basin::depth)
+                .collect::<Vec<_>>(),
+            vec![2, 4, 3, 3]
+        );
+        let labelled = segmentation.labels.iter().flatten().count();
+        assert_eq!(labelled, 3 + 9 + 14 + 9);
+        assert_eq!(segmentation.basin_at((0, 2)), None);
+        assert_eq!(segmentation.basin_at((1, 0)).map(|basin| basin.id), Some(0));
+        assert_eq!(
+            segmentation
+                .largest(3)
+                .iter()
+                .map(|basin| basin.id)
+                .collect::<Vec<_>>(),
+            vec![2, 1, 3]
+        );
+    }
+
+    #[test]
+    fn test_write_plain_basins() {
+        let data = load_data("./resources/test_data.txt");
+        let segmentation = segment(&data, &BasinRules::default());
+        let mut output = Vec::new();
+
+        write_basins(&data, &segmentation, RenderStyle::Plain, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap().lines().next(),
+            Some("aa###bbbbb")
+        );
+    }
+
+    fn sizes(segmentation: &Segmentation) -> Vec<usize> {
+        segmentation.basins.iter().map(|basin| basin.size).collect()
+    }
+
+    #[test]
+    fn test_drainage_matches_walls_on_puzzle_data() {
+        let data = load_data("./resources/test_data.txt");
+        let drainage = BasinRules {
+            mode: BasinMode::Drainage,
+            ..BasinRules::default()
+        };
+
+        let segmentation = segment(&data, &drainage);
+
+        assert_eq!(segmentation, segment(&data, &BasinRules::default()));
+    }
+
+    #[test]
+    fn test_drainage_splits_walled_basin() {
+        let data = Grid::parse_digits("1239321\n2349432\n1239321").unwrap();
+        let drainage = BasinRules {
+            mode: BasinMode::Drainage,
+            wall_height: 10,
+            ..BasinRules::default()
+        };
+
+        let segmentation = segment(&data, &drainage);
+
+        // The ridge of nines is a plateau and drains as one into the top left.
+        assert_eq!(sizes(&segmentation), vec![9, 6, 3, 3]);
+        assert_eq!(
+            segmentation
+                .basins
+                .iter()
+                .map(|basin| basin.low_point)
+                .collect::<Vec<_>>(),
+            vec![(0, 0), (0, 6), (2, 0), (2, 6)]
+        );
+        assert_eq!(sizes(&segment(&data, &BasinRules::default())), vec![9, 9]);
+    }
+
+    #[test]
+    fn test_drainage_plateaus() {
+        let drainage = BasinRules {
+            mode: BasinMode::Drainage,
+            wall_height: 10,
+            ..BasinRules::default()
+        };
+        let flat = Grid::parse_digits("555\n555").unwrap();
+        let plateau_with_exit = Grid::parse_digits("1555\n9555").unwrap();
+        let plateau_with_two_exits = Grid::parse_digits("1555\n9553").unwrap();
+
+        assert_eq!(sizes(&segment(&flat, &drainage)), vec![6]);
+        let segmentation = segment(&plateau_with_exit, &drainage);
+        assert_eq!(sizes(&segmentation), vec![8]);
+        assert_eq!(segmentation.basins[0].low_point, (0, 0));
+        // The cell next to the higher exit still drains with its plateau.
+        let segmentation = segment(&plateau_with_two_exits, &drainage);
+        assert_eq!(sizes(&segmentation), vec![7, 1]);
+        assert_eq!(segmentation.basin_at((1, 2)).map(|basin| basin.id), Some(0));
+        assert_eq!(segmentation.basins[1].low_point, (1, 3));
+    }
+
+    #[test]
+    fn test_connectivity_and_wall_height() {
+        let data = Grid::parse_digits("1919\n9191\n1919").unwrap();
+        let eight = BasinRules {
+            neighbourhood: Neighbourhood::Eight,
+            ..BasinRules::default()
+        };
+        let low_walls = BasinRules {
+            wall_height: 2,
+            ..BasinRules::default()
+        };
+
+        assert_eq!(sizes(&segment(&data, &BasinRules::default())), vec![1; 6]);
+        assert_eq!(sizes(&segment(&data, &eight)), vec![6]);
+        assert_eq!(sizes(&segment(&data, &low_walls)), vec![1; 6]);
+        assert_eq!(
+            sizes(&segment(&Grid::parse_digits("123").unwrap(), &low_walls)),
+            vec![1]
+        );
+    }
+}
@@ -1,112 +1,183 @@
 use std::{
-    collections::{HashSet, VecDeque},
-    fs::File,
-    io::{BufRead, BufReader},
+    env, fs,
+    io::{self, BufWriter, IsTerminal, Write},
 };
 
-type Area = Vec<Vec<u8>>;
+use common::grid::{parse_coordinates, Coordinates, Grid, Neighbourhood};
+
+mod basins;
+
+use basins::{Basin, BasinMode, BasinRules, RenderStyle};
+
+type Area = Grid<u8>;
 
 fn load_data(file_name: &str) -> Area {
-    let file = File::open(file_name).expect(&format!("Can't read file {}", file_name));
-    let file = BufReader::new(file);
-    file.lines()
-        .map(|line| {
-            line.unwrap()
-                .chars()
-                .map(|string_digit| string_digit.to_digit(10).unwrap() as u8)
-                .collect()
-        })
-        .collect()
+    let input =
+        fs::read_to_string(file_name).unwrap_or_else(|_| panic!("Can't read file {}", file_name));
+    Grid::parse_digits(&input).unwrap_or_else(|error| panic!("{}: {}", file_name, error))
 }
 
-fn sum_of_risk_levels<RiskCostFun>(area: &Area, risk_fun: RiskCostFun) -> usize
+fn sum_of_risk_levels<RiskCostFun>(
+    area: &Area,
+    neighbourhood: Neighbourhood,
+    risk_fun: RiskCostFun,
+) -> usize
 where
     RiskCostFun: Fn(u8) -> usize,
 {
-    let x_max = area.len();
-    let y_max = area[0].len();
-    let mask = |x: usize, y: usize| {
-        [
-            if x as i64 - 1 < 0 { 10 } else { area[x - 1][y] },
-            if y as i64 - 1 < 0 { 10 } else { area[x][y - 1] },
-            if x + 1 >= x_max { 10 } else { area[x + 1][y] },
-            if y + 1 >= y_max { 10 } else { area[x][y + 1] },
-        ]
-        .iter()
-        .all(|adjacent| *adjacent > area[x][y])
+    let is_low_point = |coordinates: Coordinates| {
+        area.neighbours(coordinates, neighbourhood)
+            .all(|adjacent| area[adjacent] > area[coordinates])
     };
-    let mut sum = 0;
-    for x in 0..x_max {
-        for y in 0..y_max {
-            sum += if mask(x, y) { risk_fun(area[x][y]) } else { 0 };
-        }
-    }
 
-    return sum;
+    area.cells()
+        .filter(|(coordinates, _)| is_low_point(*coordinates))
+        .map(|(_, height)| risk_fun(*height))
+        .sum()
 }
 
-fn find_n_largest(area: &Area, n: usize) -> Vec<HashSet<(i32, i32)>> {
-    let mut basins_points = HashSet::new();
-    for x in 0..area.len() {
-        for y in 0..area[0].len() {
-            if area[x][y] < 9 {
-                basins_points.insert((x as i32, y as i32));
-            };
-        }
-    }
-
-    let mut basins = vec![];
-    let adjacent = |x, y| VecDeque::from(vec![(x - 1, y), (x, y - 1), (x + 1, y), (x, y + 1)]);
-
-    while let Some(cord) = basins_points.iter().next() {
-        let mut cords_to_check = VecDeque::from(vec![*cord]);
-        let mut new_basin = HashSet::new();
-        while let Some(front) = cords_to_check.pop_front() {
-            if basins_points.remove(&front) {
-                cords_to_check.append(&mut adjacent(front.0, front.1));
-                new_basin.insert(front);
-            }
-        }
-        basins.push(new_basin);
-    }
-
-    basins.sort_by(|basin1, basin2| basin2.len().cmp(&basin1.len()));
-    basins.into_iter().take(n).collect()
+fn find_n_largest(area: &Area, rules: &BasinRules, n: usize) -> Vec<Basin> {
+    basins::segment(area, rules)
+        .largest(n)
+        .into_iter()
+        .cloned()
+        .collect()
 }
 
-fn part_1_result(file_name: &str) {
+fn part_1_result(file_name: &str, rules: &BasinRules) {
     let data = load_data(file_name);
     println!(
         "Part 1. Result: {}",
-        sum_of_risk_levels(&data, |val| { val as usize + 1 })
+        sum_of_risk_levels(&data, rules.neighbourhood, |val| { val as usize + 1 })
     );
 }
 
-fn part_2_result(file_name: &str) {
+fn part_2_result(file_name: &str, rules: &BasinRules) {
     let data = load_data(file_name);
     println!(
         "Part 2. Result: {}",
-        find_n_largest(&data, 3)
+        find_n_largest(&data, rules, 3)
             .into_iter()
-            .fold(1, |acc, elem| acc * elem.len())
+            .fold(1, |acc, elem| acc * elem.size)
+    );
+}
+
+#[derive(Default)]
+struct BasinOptions {
+    rules: BasinRules,
+    stats: bool,
+    at: Option<Coordinates>,
+    show: bool,
+    plain: bool,
+}
+
+impl BasinOptions {
+    fn from_args<I: Iterator<Item = String>>(mut args: I) -> Self {
+        let mut options = BasinOptions::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--wall" => {
+                    let value = args
+                        .next()
+                        .unwrap_or_else(|| panic!("Missing value for {}", arg));
+                    options.rules.wall_height = value.parse().unwrap();
+                }
+                "--diagonal" => options.rules.neighbourhood = Neighbourhood::Eight,
+                "--drainage" => options.rules.mode = BasinMode::Drainage,
+                "--stats" => options.stats = true,
+                "--at" => {
+                    let value = args
+                        .next()
+                        .unwrap_or_else(|| panic!("Missing value for {}", arg));
+                    options.at =
+                        Some(parse_coordinates(&value).unwrap_or_else(|error| panic!("{}", error)));
+                }
+                "--show" => options.show = true,
+                "--plain" => options.plain = true,
+                _ => panic!("Unknown argument {}", arg),
+            }
+        }
+        options
+    }
+
+    fn any(&self) -> bool {
+        self.stats || self.at.is_some() || self.show
+    }
+}
+
+const BASIN_STATS_HEADER: &str = "   id  size  low point  min  max  depth  mean";
+
+fn print_basin_stats(basin: &Basin) {
+    println!(
+        "{:>5} {:>5}  {:>9}  {:>3}  {:>3}  {:>5}  {:>4.2}",
+        basin.id,
+        basin.size,
+        format!("{},{}", basin.low_point.0, basin.low_point.1),
+        basin.min_height,
+        basin.max_height,
+        basin.depth(),
+        basin.mean_height()
     );
 }
 
+fn basins_result(file_name: &str, options: &BasinOptions) {
+    let data = load_data(file_name);
+    let segmentation = basins::segment(&data, &options.rules);
+
+    if options.show {
+        let style = if !options.plain && io::stdout().is_terminal() {
+            RenderStyle::Ansi
+        } else {
+            RenderStyle::Plain
+        };
+        let mut output = BufWriter::new(io::stdout().lock());
+        basins::write_basins(&data, &segmentation, style, &mut output).unwrap();
+        output.flush().unwrap();
+    }
+    if options.stats {
+        println!("Basins: {}", segmentation.basins.len());
+        println!("{}", BASIN_STATS_HEADER);
+        for basin in segmentation.largest(segmentation.basins.len()) {
+            print_basin_stats(basin);
+        }
+    }
+    if let Some(coordinates) = options.at {
+        match segmentation.basin_at(coordinates) {
+            Some(basin) => {
+                println!("{}", BASIN_STATS_HEADER);
+                print_basin_stats(basin);
+            }
+            None => println!(
+                "{},{} is not part of any basin",
+                coordinates.0, coordinates.1
+            ),
+        }
+    }
+}
+
 fn main() {
     const DATA_FILENAME: &str = "./resources/data.txt";
-    part_1_result(DATA_FILENAME);
-    part_2_result(DATA_FILENAME);
+    let basin_options = BasinOptions::from_args(env::args().skip(1));
+    if basin_options.any() {
+        basins_result(DATA_FILENAME, &basin_options);
+        return;
+    }
+    part_1_result(DATA_FILENAME, &basin_options.rules);
+    part_2_result(DATA_FILENAME, &basin_options.rules);
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{find_n_largest, load_data, sum_of_risk_levels};
+    use crate::{find_n_largest, load_data, sum_of_risk_levels, BasinRules, Neighbourhood};
 
     #[test]
     fn part_1_test_data() {
         const TEST_DATA_FILENAME: &str = "./resources/test_data.txt";
         let data = load_data(TEST_DATA_FILENAME);
-        assert_eq!(sum_of_risk_levels(&data, |val| { val as usize + 1 }), 15);
+        assert_eq!(
+            sum_of_risk_levels(&data, Neighbourhood::Four, |val| { val as usize + 1 }),
+            15
+        );
     }
 
     #[test]
@@ -114,9 +185,9 @@ mod tests {
         const TEST_DATA_FILENAME: &str = "./resources/test_data.txt";
         let data = load_data(TEST_DATA_FILENAME);
         assert_eq!(
-            find_n_largest(&data, 3)
+            find_n_largest(&data, &BasinRules::default(), 3)
                 .into_iter()
-                .fold(1, |acc, elem| acc * elem.len()),
+                .fold(1, |acc, elem| acc * elem.size),
             1134
         );
     }